@@ -96,17 +96,17 @@ impl KeyServer for KeyServerImpl {
 
 impl KeyServerCore {
 	pub fn new(config: &ClusterConfiguration, acl_storage: Arc<AclStorage>, key_storage: Arc<KeyStorage>) -> Result<Self, Error> {
-		let config = NetClusterConfiguration {
-			threads: config.threads,
-			self_key_pair: ethkey::KeyPair::from_secret_slice(&config.self_private)?,
-			listen_address: (config.listener_address.address.clone(), config.listener_address.port),
-			nodes: config.nodes.iter()
+		let config = NetClusterConfiguration::new(
+			config.threads,
+			ethkey::KeyPair::from_secret_slice(&config.self_private)?,
+			(config.listener_address.address.clone(), config.listener_address.port),
+			config.nodes.iter()
 				.map(|(node_id, node_address)| (node_id.clone(), (node_address.address.clone(), node_address.port)))
 				.collect(),
-			allow_connecting_to_higher_nodes: config.allow_connecting_to_higher_nodes,
-			acl_storage: acl_storage,
-			key_storage: key_storage,
-		};
+			config.allow_connecting_to_higher_nodes,
+			key_storage,
+			acl_storage,
+		);
 
 		let (stop, stopped) = futures::oneshot();
 		let (tx, rx) = mpsc::channel();
@@ -15,17 +15,23 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::io;
+use std::cmp;
+use std::fmt;
 use std::time;
 use std::sync::{Arc, Weak};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::collections::btree_map::Entry;
-use std::net::{SocketAddr, IpAddr};
-use futures::{finished, failed, Future, Stream, BoxFuture};
-use futures_cpupool::CpuPool;
+use std::net::{SocketAddr, IpAddr, ToSocketAddrs, Shutdown};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use futures::{self, finished, failed, lazy, Future, IntoFuture, Stream, BoxFuture, Complete};
+use futures_cpupool::{CpuPool, Builder as CpuPoolBuilder};
 use parking_lot::{RwLock, Mutex};
+use rand::{self, Rng};
+use net2::TcpBuilder;
 use tokio_io::IoFuture;
-use tokio_core::reactor::{Handle, Remote, Interval};
+use tokio_core::reactor::{Handle, Remote, Interval, Timeout};
 use tokio_core::net::{TcpListener, TcpStream};
 use ethkey::{Public, Secret, KeyPair, Signature, Random, Generator};
 use key_server_cluster::{Error, NodeId, SessionId, AclStorage, KeyStorage, DocumentEncryptedKeyShadow};
@@ -33,8 +39,10 @@ use key_server_cluster::message::{self, Message, ClusterMessage, EncryptionMessa
 use key_server_cluster::decryption_session::{SessionImpl as DecryptionSessionImpl, SessionState as DecryptionSessionState,
 	SessionParams as DecryptionSessionParams, Session as DecryptionSession, DecryptionSessionId};
 use key_server_cluster::encryption_session::{SessionImpl as EncryptionSessionImpl, SessionState as EncryptionSessionState,
-	SessionParams as EncryptionSessionParams, Session as EncryptionSession};
-use key_server_cluster::io::{DeadlineStatus, ReadMessage, SharedTcpStream, read_encrypted_message, WriteMessage, write_encrypted_message};
+	SessionParams as EncryptionSessionParams, Session as EncryptionSession, check_threshold};
+use key_server_cluster::io::{DeadlineStatus, SharedTcpStream, deadline, MESSAGE_HEADER_SIZE, MessageHeader, read_header, read_encrypted_payload, write_encrypted_message,
+	write_compressed_encrypted_message,
+	is_transient_write_error};
 use key_server_cluster::net::{accept_connection as net_accept_connection, connect as net_connect, Connection as NetConnection};
 
 /// Maintain interval (seconds). Every MAINTAIN_INTERVAL seconds node:
@@ -62,18 +70,138 @@ const ENCRYPTION_SESSION_TIMEOUT_INTERVAL: u64 = 60;
 /// session messages.
 const DECRYPTION_SESSION_TIMEOUT_INTERVAL: u64 = 60;
 
+/// When node is blacklisted, it is not allowed to connect to this node within BLACKLIST_INTERVAL seconds.
+/// After this interval, node is allowed to rejoin the cluster (i.e. in case of transient faults).
+const BLACKLIST_INTERVAL: u64 = 60;
+
+/// Every failed connection attempt doubles the interval before next connection attempt to the same node.
+/// This interval is never greater than MAX_RECONNECT_INTERVAL seconds.
+const MAX_RECONNECT_INTERVAL: u64 = 300;
+
+/// Maximal number of active (both encryption and decryption) sessions on this node.
+/// When this limit is reached, new sessions are rejected.
+const MAX_ACTIVE_SESSIONS: usize = 1024;
+
+/// Every session, which is active for longer than SESSION_TIMEOUT_INTERVAL seconds, is stopped && removed,
+/// even if it is still receiving messages.
+const SESSION_TIMEOUT_INTERVAL: u64 = 600;
+
+/// Maximal size (in bytes) of incoming message payload. Nodes, sending larger messages, are blacklisted.
+/// Message payload size is also limited by the size of header field (u16).
+const MAX_MESSAGE_SIZE: usize = 65535;
+
+/// When connection (including handshake) is not established within HANDSHAKE_TIMEOUT seconds,
+/// connection attempt is considered failed.
+const HANDSHAKE_TIMEOUT: u64 = 5;
+
+/// Interval (seconds) between TCP keep-alive probes, sent by OS on every idle cluster connection.
+const TCP_KEEPALIVE_INTERVAL: u64 = 30;
+
+/// When MAX_PROTOCOL_ERRORS messages in a row, received from the node, are malformed, connection to this node is closed.
+const MAX_PROTOCOL_ERRORS: usize = 5;
+
+/// When Pong message is not received within PING_TIMEOUT seconds after Ping message has been sent, ping fails.
+const PING_TIMEOUT: u64 = 10;
+
+/// When message payload is not received within READ_TIMEOUT seconds after message header,
+/// connection is considered stalled && closed.
+const READ_TIMEOUT: u64 = 10;
+
+/// Number of times message write is retried after transient error, before connection is closed.
+const MAX_WRITE_RETRIES: usize = 3;
+
+/// Delay (in milliseconds) before the first write retry. Every next retry doubles the delay.
+const WRITE_RETRY_INTERVAL: u64 = 50;
+
+/// Max delay (in milliseconds) before write retry.
+const MAX_WRITE_RETRY_INTERVAL: u64 = 5000;
+
+/// When message is not written to the connection within SEND_TIMEOUT seconds, connection is closed.
+const SEND_TIMEOUT: u64 = 10;
+
+/// Maximal delay (in milliseconds) before connecting to nodes with lower ids on start. These nodes
+/// are expected to connect to this node => delay reduces number of duplicate connections.
+const INITIAL_CONNECT_DELAY: u64 = 1000;
+
+/// Maximal number of pending (not yet accepted) inbound connections.
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Maximal number of connection events, remembered by the node. Oldest events are dropped first.
+const MAX_CONNECTION_EVENTS: usize = 1024;
+
+/// Messages with payload of at least COMPRESSION_THRESHOLD bytes are compressed (when compression is supported by both nodes).
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Node is considered ready to serve requests when it is connected to at least READY_QUORUM_PERCENT percents
+/// of other cluster nodes.
+const READY_QUORUM_PERCENT: usize = 50;
+
 /// Encryption sesion timeout interval. It works
 /// Empty future.
 type BoxedEmptyFuture = BoxFuture<(), ()>;
 
+/// Source of current time.
+pub trait Clock: Send + Sync {
+	/// Get current time.
+	fn now(&self) -> time::Instant;
+}
+
+/// Clock, which reports system time.
+#[derive(Default)]
+pub struct SystemClock;
+
 /// Cluster interface for external clients.
 pub trait ClusterClient: Send + Sync {
 	/// Get cluster state.
 	fn cluster_state(&self) -> ClusterState;
+	/// Get id of this node.
+	fn self_node_id(&self) -> NodeId;
+	/// Get all other cluster nodes along with addresses, used to connect to these nodes.
+	fn configured_nodes(&self) -> BTreeMap<NodeId, (String, u16)>;
+	/// Set listener of sessions lifecycle events.
+	fn set_event_sink(&self, event_sink: Arc<EventSink>);
+	/// Allow or forbid starting new sessions on this node. Active sessions are not affected.
+	fn set_accepting_sessions(&self, is_accepting: bool);
+	/// Check if connection to given node is established.
+	fn is_connected(&self, node: &NodeId) -> bool;
+	/// Get snapshot of active sessions.
+	fn sessions_snapshot(&self) -> SessionsSnapshot;
+	/// Get report on every active session.
+	fn session_report(&self) -> Vec<SessionReport>;
+	/// Get parameters of active encryption session. None if session is unknown or is not yet initialized.
+	fn session_info(&self, session_id: &SessionId) -> Option<SessionInfo>;
+	/// Get summary of node health.
+	fn health(&self) -> ClusterHealth;
+	/// Wait until at least given number of other nodes are connected. Fails with QuorumTimeout after given time.
+	fn wait_for_quorum(&self, min_nodes: usize, timeout: time::Duration) -> BoxFuture<(), Error>;
+	/// Check liveness of all connections immediately, without waiting for the next maintain.
+	fn run_keep_alive_now(&self);
+	/// Mark connection to given node as active right now, as if message has been received from this node.
+	fn touch_connection(&self, node: &NodeId) -> Result<(), Error>;
+	/// Measure round-trip time to given node. Fails with PingTimeout when there is no response within ping timeout.
+	fn ping(&self, node: &NodeId) -> BoxFuture<time::Duration, Error>;
+	/// Get traffic statistics of every active connection.
+	fn connection_stats(&self) -> BTreeMap<NodeId, ConnectionStats>;
+	/// Get outcomes of all connection establishment attempts.
+	fn connection_metrics(&self) -> ConnectionMetrics;
+	/// Get up to `limit` most recent connection events, oldest first.
+	fn recent_connection_events(&self, limit: usize) -> Vec<ConnectionEvent>;
 	/// Start new encryption session.
 	fn new_encryption_session(&self, session_id: SessionId, threshold: usize) -> Result<Arc<EncryptionSession>, Error>;
+	/// Start new encryption session on given subset of connected nodes. Subset must include this node.
+	fn new_encryption_session_with_nodes(&self, session_id: SessionId, threshold: usize, nodes: BTreeSet<NodeId>) -> Result<Arc<EncryptionSession>, Error>;
+	/// Start new encryption session. Returned future is resolved when session is completed.
+	fn new_encryption_session_future(&self, session_id: SessionId, threshold: usize) -> BoxFuture<Public, Error>;
 	/// Start new decryption session.
 	fn new_decryption_session(&self, session_id: SessionId, requestor_signature: Signature, is_shadow_decryption: bool) -> Result<Arc<DecryptionSession>, Error>;
+	/// Forcibly stop active encryption session and ask other participants to do the same.
+	fn abort_session(&self, session_id: &SessionId) -> Result<(), Error>;
+	/// Forcibly stop active decryption session and ask other participants to do the same.
+	fn abort_decryption_session(&self, session_id: &DecryptionSessionId) -> Result<(), Error>;
+	/// Add new node to the cluster && try to connect to it.
+	fn add_node(&self, node: NodeId, address: (String, u16)) -> Result<(), Error>;
+	/// Remove node from the cluster && close connection to it.
+	fn remove_node(&self, node: &NodeId);
 
 	#[cfg(test)]
 	/// Ask node to make 'faulty' encryption sessions.
@@ -86,12 +214,28 @@ pub trait ClusterClient: Send + Sync {
 	fn connect(&self);
 }
 
+/// Listener of encryption sessions lifecycle events.
+pub trait EventSink: Send + Sync {
+	/// Called when new encryption session is started on this node.
+	fn on_session_started(&self, session_id: SessionId);
+	/// Called when encryption session is completed on this node.
+	fn on_session_completed(&self, session_id: SessionId);
+	/// Called when encryption session has failed on this node.
+	fn on_session_failed(&self, session_id: SessionId, error: Error);
+}
+
 /// Cluster access for single encryption/decryption participant.
 pub trait Cluster: Send + Sync {
 	/// Broadcast message to all other nodes.
 	fn broadcast(&self, message: Message) -> Result<(), Error>;
+	/// Broadcast message to all other nodes, except given one.
+	fn broadcast_except(&self, exclude: &NodeId, message: Message) -> Result<(), Error>;
+	/// Broadcast message to all other nodes. Returns delivery status for every node.
+	fn broadcast_detailed(&self, message: Message) -> BTreeMap<NodeId, Result<(), Error>>;
 	/// Send message to given node.
 	fn send(&self, to: &NodeId, message: Message) -> Result<(), Error>;
+	/// Blacklist misbehaving node: close connection to this node && refuse its connections for a while.
+	fn blacklist(&self, node: &NodeId);
 }
 
 #[derive(Clone)]
@@ -99,18 +243,75 @@ pub trait Cluster: Send + Sync {
 pub struct ClusterConfiguration {
 	/// Number of threads reserved by cluster.
 	pub threads: usize,
+	/// Prefix of names of threads, reserved by cluster.
+	pub threads_name_prefix: String,
 	/// Allow connecting to 'higher' nodes.
 	pub allow_connecting_to_higher_nodes: bool,
+	/// Initiate outbound connections. When false, node is only accepting connections from other nodes.
+	pub connect_outbound: bool,
 	/// KeyPair this node holds.
 	pub self_key_pair: KeyPair,
 	/// Interface to listen to.
 	pub listen_address: (String, u16),
 	/// Cluster nodes.
 	pub nodes: BTreeMap<NodeId, (String, u16)>,
+	/// Addresses, which must be used to connect to given nodes instead of addresses from `nodes`.
+	/// Useful when node is behind NAT or proxy && listens at address, which differs from its public address.
+	pub advertised_addresses: BTreeMap<NodeId, (String, u16)>,
+	/// Order, in which nodes are visited when message is broadcasted. Nodes, missing from this list,
+	/// are visited after listed nodes, in order of their ids. Empty list means order of ids.
+	pub nodes_order: Vec<NodeId>,
 	/// Reference to key storage
 	pub key_storage: Arc<KeyStorage>,
 	/// Reference to ACL storage
 	pub acl_storage: Arc<AclStorage>,
+	/// Source of current time.
+	pub clock: Arc<Clock>,
+	/// Interval, within which blacklisted node is not allowed to connect.
+	pub blacklist_interval: time::Duration,
+	/// When no messages have been received from node within this interval, KeepAlive message is sent to the node.
+	pub keep_alive_interval: time::Duration,
+	/// When no messages have been received from node within this interval, node is disconnected.
+	pub disconnect_timeout: time::Duration,
+	/// Interval between maintain procedures.
+	pub maintain_interval: time::Duration,
+	/// Maximal number of active sessions on this node.
+	pub max_sessions: usize,
+	/// Sessions, which are active for longer than this interval, are removed.
+	pub session_timeout: time::Duration,
+	/// Maximal size of incoming message payload.
+	pub max_message_size: usize,
+	/// Interval between TCP keep-alive probes.
+	pub tcp_keepalive_interval: time::Duration,
+	/// Timeout for establishing connection (including handshake).
+	pub handshake_timeout: time::Duration,
+	/// Hide IP addresses of other nodes in logs.
+	pub redact_node_addresses: bool,
+	/// Minimal percent of other cluster nodes, which must be connected for this node to be ready.
+	pub ready_quorum_percent: usize,
+	/// Maximal number of consecutive malformed messages, after which connection is closed.
+	pub max_protocol_errors: usize,
+	/// Time to wait for response to Ping message.
+	pub ping_timeout: time::Duration,
+	/// Maximal number of inbound connections (including connections with handshake in progress).
+	pub max_inbound_connections: usize,
+	/// When message payload is not received within this interval after message header, connection is closed.
+	pub read_timeout: time::Duration,
+	/// Number of times message write is retried after transient error.
+	pub max_write_retries: usize,
+	/// Delay before the first write retry.
+	pub write_retry_interval: time::Duration,
+	/// When single message is not written within this interval, connection is closed.
+	pub send_timeout: time::Duration,
+	/// Maximal random delay before connecting to nodes with lower ids on start.
+	pub initial_connect_delay: time::Duration,
+	/// Maximal number of pending (not yet accepted) inbound connections.
+	pub listen_backlog: i32,
+	/// Compress messages with payload of at least this size, if peer supports compression. None disables compression.
+	/// Ignored, unless `compression` feature is enabled.
+	pub compression_threshold: Option<usize>,
+	/// Maximal number of remembered connection events.
+	pub max_connection_events: usize,
 }
 
 /// Cluster state.
@@ -119,6 +320,140 @@ pub struct ClusterState {
 	pub connected: BTreeSet<NodeId>,
 }
 
+/// Summary of node health.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterHealth {
+	/// Is node connected to the quorum of other nodes.
+	pub has_quorum: bool,
+	/// Number of other nodes, to which connections are established.
+	pub connected_nodes: usize,
+	/// Number of other nodes in the cluster configuration.
+	pub configured_nodes: usize,
+	/// Number of active (both encryption and decryption) sessions.
+	pub active_sessions: usize,
+	/// Age of the oldest active session.
+	pub oldest_session_age: Option<time::Duration>,
+	/// Uptime of the most recently established connection.
+	pub shortest_connection_uptime: Option<time::Duration>,
+}
+
+impl ClusterHealth {
+	/// Is node ready to serve requests.
+	pub fn is_ready(&self) -> bool {
+		self.has_quorum
+	}
+}
+
+/// Active sessions snapshot.
+pub struct SessionsSnapshot {
+	/// Active encryption sessions.
+	pub encryption_sessions: Vec<SessionId>,
+	/// Active decryption sessions.
+	pub decryption_sessions: Vec<DecryptionSessionId>,
+}
+
+/// Kind of active session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionKind {
+	/// Encryption session.
+	Encryption,
+	/// Decryption session.
+	Decryption,
+}
+
+/// Report on single active session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionReport {
+	/// Session id.
+	pub id: SessionId,
+	/// Decryption sub-session id. None for encryption sessions.
+	pub sub_session_id: Option<Secret>,
+	/// Session kind.
+	pub kind: SessionKind,
+	/// Session creation time.
+	pub creation_time: time::Instant,
+	/// Current session state.
+	pub state: String,
+	/// Number of nodes, participating in session.
+	pub participants: usize,
+}
+
+/// Parameters of single encryption session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionInfo {
+	/// Session master.
+	pub master: NodeId,
+	/// Session threshold.
+	pub threshold: usize,
+	/// Nodes, participating in session.
+	pub nodes: BTreeSet<NodeId>,
+}
+
+/// Connection traffic statistics.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectionStats {
+	/// Number of messages sent over connection.
+	pub messages_sent: usize,
+	/// Number of messages received over connection.
+	pub messages_received: usize,
+	/// Number of bytes sent over connection (including message headers).
+	pub bytes_sent: usize,
+	/// Number of bytes received over connection (including message headers).
+	pub bytes_received: usize,
+	/// Number of received messages, which have been rejected without processing.
+	pub messages_rejected: usize,
+	/// Time elapsed since connection has been established.
+	pub uptime: time::Duration,
+}
+
+/// Connection establishment metrics.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectionMetrics {
+	/// Outcomes of inbound connection attempts.
+	pub inbound: ConnectionAttempts,
+	/// Outcomes of outbound connection attempts.
+	pub outbound: ConnectionAttempts,
+}
+
+/// Connection state transition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionEvent {
+	/// Time of transition.
+	pub time: time::Instant,
+	/// Transition itself.
+	pub kind: ConnectionEventKind,
+}
+
+/// Kind of connection state transition.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEventKind {
+	/// Connection to given node has been established.
+	Connected(NodeId),
+	/// Connection to given node has been closed.
+	Disconnected(NodeId),
+	/// Handshake with node at given address has failed.
+	HandshakeFailed(SocketAddr),
+	/// Given node has been blacklisted.
+	Blacklisted(NodeId),
+}
+
+/// Outcomes of connection establishment attempts.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConnectionAttempts {
+	/// Number of attempts, which have ended with established connection.
+	pub connects_succeeded: usize,
+	/// Number of attempts, which have failed because of network or handshake error.
+	pub connects_failed: usize,
+	/// Number of attempts, which have not completed in time.
+	pub connects_timed_out: usize,
+	/// Number of attempts, which have been rejected because other node is unknown.
+	pub handshakes_rejected: usize,
+	/// Number of established connections, which have been dropped, because there's already connection to the same node.
+	pub duplicates_dropped: usize,
+	/// Number of established connections to nodes, connection to which has been lost before.
+	pub connections_restored: usize,
+}
+
 /// Network cluster implementation.
 pub struct ClusterCore {
 	/// Handle to the event loop.
@@ -140,6 +475,57 @@ pub struct ClusterView {
 	core: Arc<Mutex<ClusterViewCore>>,
 }
 
+/// Cluster, which silently drops all sent messages. Allows running sessions in isolation,
+/// when only local state transitions are of interest.
+///
+/// ```
+/// extern crate ethkey;
+/// extern crate ethcore_secretstore;
+///
+/// use std::collections::{BTreeSet, HashMap};
+/// use std::sync::{Arc, Mutex};
+/// use ethkey::{Random, Generator};
+/// use ethcore_secretstore::{DocumentAddress, DocumentKeyShare, Error, KeyStorage, NullCluster,
+/// 	EncryptionSession, EncryptionSessionImpl, EncryptionSessionParams, EncryptionSessionState};
+///
+/// #[derive(Default)]
+/// struct MemoryKeyStorage(Mutex<HashMap<DocumentAddress, DocumentKeyShare>>);
+///
+/// impl KeyStorage for MemoryKeyStorage {
+/// 	fn insert(&self, document: DocumentAddress, key: DocumentKeyShare) -> Result<(), Error> {
+/// 		self.0.lock().unwrap().insert(document, key);
+/// 		Ok(())
+/// 	}
+///
+/// 	fn get(&self, document: &DocumentAddress) -> Result<DocumentKeyShare, Error> {
+/// 		self.0.lock().unwrap().get(document).cloned().ok_or(Error::DocumentNotFound)
+/// 	}
+///
+/// 	fn contains(&self, document: &DocumentAddress) -> bool {
+/// 		self.0.lock().unwrap().contains_key(document)
+/// 	}
+/// }
+///
+/// fn main() {
+/// 	let nodes: BTreeSet<_> = (0..2).map(|_| Random.generate().unwrap().public().clone()).collect();
+/// 	let session = EncryptionSessionImpl::new(EncryptionSessionParams {
+/// 		id: Default::default(),
+/// 		self_node_id: nodes.iter().next().unwrap().clone(),
+/// 		key_storage: Arc::new(MemoryKeyStorage::default()),
+/// 		cluster: Arc::new(NullCluster),
+/// 	});
+///
+/// 	// InitializeSession is dropped => session waits for confirmation forever
+/// 	session.initialize(0, nodes).unwrap();
+/// 	match session.state() {
+/// 		EncryptionSessionState::WaitingForInitializationConfirm(_) => (),
+/// 		_ => panic!("unexpected session state"),
+/// 	}
+/// }
+/// ```
+#[derive(Default)]
+pub struct NullCluster;
+
 /// Cross-thread shareable cluster data.
 pub struct ClusterData {
 	/// Cluster configuration.
@@ -154,16 +540,55 @@ pub struct ClusterData {
 	connections: ClusterConnections,
 	/// Active sessions data.
 	sessions: ClusterSessions,
+	/// Sessions lifecycle events listener.
+	event_sink: RwLock<Option<Arc<EventSink>>>,
+	/// Sent Ping messages, waiting for response, by nonce.
+	pending_pings: Mutex<BTreeMap<u64, (time::Instant, Complete<Result<time::Duration, Error>>)>>,
+	/// Callers, waiting for given number of nodes to be connected, by waiter id.
+	quorum_waiters: Mutex<BTreeMap<u64, (usize, Complete<Result<(), Error>>)>>,
+	/// Are new sessions accepted by this node.
+	accepting_sessions: AtomicBool,
+	/// Indices of nodes from configured nodes order.
+	nodes_indices: BTreeMap<NodeId, usize>,
+	/// Source of current time.
+	clock: Arc<Clock>,
+	#[cfg(test)]
+	/// Procedure, which is called on every maintain.
+	maintain_hook: Mutex<Option<Box<Fn() + Send>>>,
 }
 
 /// Connections that are forming the cluster.
 pub struct ClusterConnections {
 	/// Self node id.
 	pub self_node_id: NodeId,
-	/// All known other key servers.
-	pub nodes: BTreeMap<NodeId, SocketAddr>,
+	/// Address, this node is listening at.
+	listen_address: SocketAddr,
+	/// All known other key servers. Addresses are resolved on every connection attempt.
+	pub nodes: RwLock<BTreeMap<NodeId, (String, u16)>>,
 	/// Active connections to key servers.
 	pub connections: RwLock<BTreeMap<NodeId, Arc<Connection>>>,
+	/// Blacklisted nodes along with the time until which they're blacklisted.
+	pub blacklisted: RwLock<BTreeMap<NodeId, time::Instant>>,
+	/// Disconnected nodes along with the time of next connection attempt && current reconnect interval.
+	pub backoff: RwLock<BTreeMap<NodeId, (time::Instant, time::Duration)>>,
+	/// Nodes, to which outbound connection attempt is currently in progress.
+	pub pending_connects: Mutex<BTreeSet<NodeId>>,
+	/// Outcomes of inbound connection attempts.
+	inbound_attempts: ConnectionAttemptsCounters,
+	/// Outcomes of outbound connection attempts.
+	outbound_attempts: ConnectionAttemptsCounters,
+	/// Number of inbound connections, for which handshake is currently in progress.
+	pending_accepts: AtomicUsize,
+	/// Maximal number of inbound connections.
+	max_inbound_connections: usize,
+	/// Nodes, to which connection has been established at least once.
+	ever_connected: RwLock<BTreeSet<NodeId>>,
+	/// Most recent connection events, oldest first.
+	events: Mutex<VecDeque<ConnectionEvent>>,
+	/// Maximal number of remembered connection events.
+	max_events: usize,
+	/// Source of current time.
+	clock: Arc<Clock>,
 }
 
 /// Active sessions on this cluster.
@@ -171,7 +596,7 @@ pub struct ClusterSessions {
 	/// Self node id.
 	pub self_node_id: NodeId,
 	/// All nodes ids.
-	pub nodes: BTreeSet<NodeId>,
+	pub nodes: RwLock<BTreeSet<NodeId>>,
 	/// Reference to key storage
 	pub key_storage: Arc<KeyStorage>,
 	/// Reference to ACL storage
@@ -180,8 +605,12 @@ pub struct ClusterSessions {
 	pub encryption_sessions: RwLock<BTreeMap<SessionId, QueuedEncryptionSession>>,
 	/// Active decryption sessions.
 	pub decryption_sessions: RwLock<BTreeMap<DecryptionSessionId, QueuedDecryptionSession>>,
+	/// Maximal number of active sessions.
+	pub max_sessions: usize,
 	/// Make faulty encryption sessions.
 	pub make_faulty_encryption_sessions: AtomicBool,
+	/// Source of current time.
+	pub clock: Arc<Clock>,
 }
 
 /// Encryption session and its message queue.
@@ -190,6 +619,8 @@ pub struct QueuedEncryptionSession {
 	pub master: NodeId,
 	/// Cluster view.
 	pub cluster_view: Arc<ClusterView>,
+	/// Session creation time.
+	pub creation_time: time::Instant,
 	/// Last received message time.
 	pub last_message_time: time::Instant,
 	/// Encryption session.
@@ -204,6 +635,8 @@ pub struct QueuedDecryptionSession {
 	pub master: NodeId,
 	/// Cluster view.
 	pub cluster_view: Arc<ClusterView>,
+	/// Session creation time.
+	pub creation_time: time::Instant,
 	/// Last received message time.
 	pub last_message_time: time::Instant,
 	/// Decryption session.
@@ -216,6 +649,8 @@ pub struct QueuedDecryptionSession {
 struct ClusterViewCore {
 	/// Cluster reference.
 	cluster: Arc<ClusterData>,
+	/// This node id.
+	self_node_id: NodeId,
 	/// Subset of nodes, required for this session.
 	nodes: BTreeSet<NodeId>,
 }
@@ -228,12 +663,64 @@ pub struct Connection {
 	node_address: SocketAddr,
 	/// Is inbound connection?
 	is_inbound: bool,
+	/// Hide node IP address when displaying connection?
+	redact_address: bool,
 	/// Tcp stream.
 	stream: SharedTcpStream,
 	/// Connection key.
 	key: KeyPair,
 	/// Last message time.
 	last_message_time: Mutex<time::Instant>,
+	/// Time when connection has been established.
+	established_at: time::Instant,
+	/// Time when header of currently read message has been received.
+	payload_read_started: Arc<Mutex<Option<time::Instant>>>,
+	/// Traffic counters.
+	counters: Arc<ConnectionCounters>,
+	/// Source of current time.
+	clock: Arc<Clock>,
+	/// Compress messages with payload of at least this size. None if this node doesn't support compression.
+	compression_threshold: Option<usize>,
+	/// Has peer announced that it supports compression?
+	is_compression_supported_by_peer: AtomicBool,
+	/// Nonce of the last KeepAlive message, sent over connection.
+	last_sent_keep_alive_nonce: AtomicUsize,
+	/// Nonce of the last KeepAlive message, received over connection.
+	last_received_keep_alive_nonce: Mutex<u64>,
+}
+
+/// Connection traffic counters.
+#[derive(Default)]
+struct ConnectionCounters {
+	/// Number of messages sent.
+	messages_sent: AtomicUsize,
+	/// Number of messages received.
+	messages_received: AtomicUsize,
+	/// Number of bytes sent.
+	bytes_sent: AtomicUsize,
+	/// Number of bytes received.
+	bytes_received: AtomicUsize,
+	/// Number of rejected messages.
+	messages_rejected: AtomicUsize,
+	/// Number of malformed messages, received in a row.
+	protocol_errors: AtomicUsize,
+}
+
+/// Connection establishment attempts counters.
+#[derive(Default)]
+struct ConnectionAttemptsCounters {
+	/// Number of succeeded attempts.
+	connects_succeeded: AtomicUsize,
+	/// Number of failed attempts.
+	connects_failed: AtomicUsize,
+	/// Number of timed out attempts.
+	connects_timed_out: AtomicUsize,
+	/// Number of rejected handshakes.
+	handshakes_rejected: AtomicUsize,
+	/// Number of dropped duplicate connections.
+	duplicates_dropped: AtomicUsize,
+	/// Number of restored connections.
+	connections_restored: AtomicUsize,
 }
 
 /// Encryption session implementation, which removes session from cluster on drop.
@@ -258,12 +745,70 @@ struct DecryptionSessionWrapper {
 	cluster: Weak<ClusterData>,
 }
 
+impl Clock for SystemClock {
+	fn now(&self) -> time::Instant {
+		time::Instant::now()
+	}
+}
+
+impl ClusterConfiguration {
+	/// Create new cluster configuration with default values of optional parameters.
+	pub fn new(threads: usize, self_key_pair: KeyPair, listen_address: (String, u16), nodes: BTreeMap<NodeId, (String, u16)>,
+		allow_connecting_to_higher_nodes: bool, key_storage: Arc<KeyStorage>, acl_storage: Arc<AclStorage>) -> Self {
+		// every node is connecting to us at most once => limit inbound connections by the number of nodes
+		let max_inbound_connections = nodes.len();
+		let threads_name_prefix = default_threads_name_prefix(self_key_pair.public());
+		ClusterConfiguration {
+			threads: threads,
+			threads_name_prefix: threads_name_prefix,
+			allow_connecting_to_higher_nodes: allow_connecting_to_higher_nodes,
+			connect_outbound: true,
+			self_key_pair: self_key_pair,
+			listen_address: listen_address,
+			nodes: nodes,
+			advertised_addresses: BTreeMap::new(),
+			nodes_order: Vec::new(),
+			key_storage: key_storage,
+			acl_storage: acl_storage,
+			clock: Arc::new(SystemClock),
+			blacklist_interval: time::Duration::from_secs(BLACKLIST_INTERVAL),
+			keep_alive_interval: time::Duration::from_secs(KEEP_ALIVE_SEND_INTERVAL),
+			disconnect_timeout: time::Duration::from_secs(KEEP_ALIVE_DISCONNECT_INTERVAL),
+			maintain_interval: time::Duration::from_secs(MAINTAIN_INTERVAL),
+			max_sessions: MAX_ACTIVE_SESSIONS,
+			session_timeout: time::Duration::from_secs(SESSION_TIMEOUT_INTERVAL),
+			max_message_size: MAX_MESSAGE_SIZE,
+			tcp_keepalive_interval: time::Duration::from_secs(TCP_KEEPALIVE_INTERVAL),
+			handshake_timeout: time::Duration::from_secs(HANDSHAKE_TIMEOUT),
+			redact_node_addresses: false,
+			ready_quorum_percent: READY_QUORUM_PERCENT,
+			max_protocol_errors: MAX_PROTOCOL_ERRORS,
+			ping_timeout: time::Duration::from_secs(PING_TIMEOUT),
+			max_inbound_connections: max_inbound_connections,
+			read_timeout: time::Duration::from_secs(READ_TIMEOUT),
+			max_write_retries: MAX_WRITE_RETRIES,
+			write_retry_interval: time::Duration::from_millis(WRITE_RETRY_INTERVAL),
+			send_timeout: time::Duration::from_secs(SEND_TIMEOUT),
+			initial_connect_delay: time::Duration::from_millis(INITIAL_CONNECT_DELAY),
+			listen_backlog: LISTEN_BACKLOG,
+			compression_threshold: Some(COMPRESSION_THRESHOLD),
+			max_connection_events: MAX_CONNECTION_EVENTS,
+		}
+	}
+}
+
 impl ClusterCore {
 	pub fn new(handle: Handle, config: ClusterConfiguration) -> Result<Arc<Self>, Error> {
+		let pool = make_pool(&config);
+		ClusterCore::new_with_pool(handle, config, pool)
+	}
+
+	/// Create new cluster, which is using given thread pool. The `threads` configuration parameter is ignored.
+	pub fn new_with_pool(handle: Handle, config: ClusterConfiguration, pool: CpuPool) -> Result<Arc<Self>, Error> {
 		let listen_address = make_socket_address(&config.listen_address.0, config.listen_address.1)?;
 		let connections = ClusterConnections::new(&config)?;
 		let sessions = ClusterSessions::new(&config);
-		let data = ClusterData::new(&handle, config, connections, sessions);
+		let data = ClusterData::new(&handle, config, pool, connections, sessions);
 
 		Ok(Arc::new(ClusterCore {
 			handle: handle,
@@ -310,29 +855,59 @@ impl ClusterCore {
 	/// Start connecting to other nodes.
 	pub fn run_connections(&self) -> Result<(), Error> {
 		// try to connect to every other peer
-		ClusterCore::connect_disconnected_nodes(self.data.clone());
+		// nodes with lower ids are expected to connect to us => give them a chance to do it first
+		for (node_id, node_address) in self.data.connections.disconnected_nodes() {
+			if should_keep_connection(self.data.self_key_pair.public(), &node_id, false) {
+				ClusterCore::connect_node(self.data.clone(), &node_id, node_address);
+			} else {
+				let delay = random_delay(self.data.config.initial_connect_delay);
+				ClusterCore::connect_node_after(self.data.clone(), node_id, node_address, delay);
+			}
+		}
 		Ok(())
 	}
 
+	/// Resolve peer address && connect to peer.
+	fn connect(data: Arc<ClusterData>, node_id: NodeId, node_address: (String, u16)) {
+		// address resolution may block => do it in the thread pool
+		data.clone().spawn(lazy(move || {
+			match make_socket_address(&node_address.0, node_address.1) {
+				Ok(socket_address) => {
+					ClusterCore::connect_to(data, node_id, socket_address);
+					Ok(())
+				},
+				Err(err) => {
+					warn!(target: "secretstore_net", "{}: failed to resolve node address {}:{}", data.self_key_pair.public(), node_address.0, node_address.1);
+					data.connections.finish_connecting(&node_id);
+					Err(err)
+				},
+			}
+		}));
+	}
+
 	/// Connect to peer.
-	fn connect(data: Arc<ClusterData>, node_address: SocketAddr) {
+	fn connect_to(data: Arc<ClusterData>, node_id: NodeId, node_address: SocketAddr) {
 		data.handle.clone().spawn(move |handle| {
-			data.pool.clone().spawn(ClusterCore::connect_future(handle, data, node_address))
+			data.pool.clone().spawn(ClusterCore::connect_future(handle, data, node_id, node_address))
 		})
 	}
 
 	/// Connect to socket using given context and handle.
-	fn connect_future(handle: &Handle, data: Arc<ClusterData>, node_address: SocketAddr) -> BoxedEmptyFuture {
+	fn connect_future(handle: &Handle, data: Arc<ClusterData>, node_id: NodeId, node_address: SocketAddr) -> BoxedEmptyFuture {
 		let disconnected_nodes = data.connections.disconnected_nodes().keys().cloned().collect();
-		net_connect(&node_address, handle, data.self_key_pair.clone(), disconnected_nodes)
-			.then(move |result| ClusterCore::process_connection_result(data, false, result))
+		net_connect(&node_address, handle, data.self_key_pair.clone(), disconnected_nodes, data.config.handshake_timeout, data.config.tcp_keepalive_interval)
+			.then(move |result| {
+				data.connections.finish_connecting(&node_id);
+				ClusterCore::process_connection_result(data, false, node_address, result)
+			})
 			.then(|_| finished(()))
 			.boxed()
 	}
 
 	/// Start listening for incoming connections.
 	fn listen(handle: &Handle, data: Arc<ClusterData>, listen_address: SocketAddr) -> Result<BoxedEmptyFuture, Error> {
-		Ok(TcpListener::bind(&listen_address, &handle)?
+		let listener = make_listener(&listen_address, data.config.listen_backlog)?;
+		Ok(TcpListener::from_listener(listener, &listen_address, &handle)?
 			.incoming()
 			.and_then(move |(stream, node_address)| {
 				ClusterCore::accept_connection(data.clone(), stream, node_address);
@@ -345,6 +920,12 @@ impl ClusterCore {
 
 	/// Accept connection.
 	fn accept_connection(data: Arc<ClusterData>, stream: TcpStream, node_address: SocketAddr) {
+		if !data.connections.start_accepting() {
+			// stream is closed when dropped
+			warn!(target: "secretstore_net", "{}: refusing connection from {}: too many inbound connections", data.self_key_pair.public(), node_address);
+			return;
+		}
+
 		data.handle.clone().spawn(move |handle| {
 			data.pool.clone().spawn(ClusterCore::accept_connection_future(handle, data, stream, node_address))
 		})
@@ -353,54 +934,160 @@ impl ClusterCore {
 	/// Accept connection future.
 	fn accept_connection_future(handle: &Handle, data: Arc<ClusterData>, stream: TcpStream, node_address: SocketAddr) -> BoxedEmptyFuture {
 		let disconnected_nodes = data.connections.disconnected_nodes().keys().cloned().collect();
-		net_accept_connection(node_address, stream, handle, data.self_key_pair.clone(), disconnected_nodes)
-			.then(move |result| ClusterCore::process_connection_result(data, true, result))
+		net_accept_connection(node_address, stream, handle, data.self_key_pair.clone(), disconnected_nodes, data.config.handshake_timeout, data.config.tcp_keepalive_interval)
+			.then(move |result| {
+				data.connections.finish_accepting();
+				ClusterCore::process_connection_result(data, true, node_address, result)
+			})
 			.then(|_| finished(()))
 			.boxed()
 	}
 
-	/// Schedule mainatain procedures.
+	/// Schedule mainatain procedures. Maintain is stopped once cluster data is dropped.
 	fn schedule_maintain(handle: &Handle, data: Arc<ClusterData>) {
-		let d = data.clone();
-		let interval: BoxedEmptyFuture = Interval::new(time::Duration::new(MAINTAIN_INTERVAL, 0), handle)
-			.expect("failed to create interval")
-			.and_then(move |_| Ok(ClusterCore::maintain(data.clone())))
-			.for_each(|_| Ok(()))
-			.then(|_| finished(()))
+		ClusterCore::start_maintain(handle, Arc::downgrade(&data));
+	}
+
+	/// Start maintain interval.
+	fn start_maintain(handle: &Handle, data: Weak<ClusterData>) {
+		let cluster_data = match data.upgrade() {
+			Some(cluster_data) => cluster_data,
+			None => return,
+		};
+
+		let interval = match Interval::new(cluster_data.config.maintain_interval, handle) {
+			Ok(interval) => interval,
+			Err(err) => {
+				warn!(target: "secretstore_net", "{}: failed to create maintain interval: {}. Retrying", cluster_data.self_key_pair.public(), err);
+				ClusterCore::restart_maintain(data);
+				return;
+			},
+		};
+
+		let maintain_data = data.clone();
+		let interval: BoxedEmptyFuture = interval
+			.map(move |_| maintain_data.upgrade())
+			// cluster data is dropped => stop maintain
+			.take_while(|maintain_data| Ok(maintain_data.is_some()))
+			.for_each(|maintain_data| {
+				if let Some(maintain_data) = maintain_data {
+					ClusterCore::maintain(maintain_data);
+				}
+				Ok(())
+			})
+			.then(move |result| {
+				// interval stream is only finished when cluster data is dropped => recreate it to keep node alive
+				if let Some(restart_data) = data.upgrade() {
+					warn!(target: "secretstore_net", "{}: maintain interval has stopped ({:?}). Restarting", restart_data.self_key_pair.public(), result.err());
+					ClusterCore::restart_maintain(data);
+				}
+				finished(())
+			})
 			.boxed();
 
-		d.spawn(interval);
+		cluster_data.spawn(interval);
+	}
+
+	/// Start maintain interval after maintain interval has passed.
+	fn restart_maintain(data: Weak<ClusterData>) {
+		let (handle, delay) = match data.upgrade() {
+			Some(cluster_data) => (cluster_data.handle.clone(), cluster_data.config.maintain_interval),
+			None => return,
+		};
+
+		handle.spawn(move |handle| {
+			let handle = handle.clone();
+			Timeout::new(delay, &handle)
+				.into_future()
+				.flatten()
+				.then(move |result| {
+					match result {
+						Ok(_) => ClusterCore::start_maintain(&handle, data),
+						Err(err) => warn!(target: "secretstore_net", "failed to create maintain restart timeout: {}", err),
+					}
+					finished(())
+				})
+		});
 	}
 
 	/// Execute maintain procedures.
 	fn maintain(data: Arc<ClusterData>) {
 		trace!(target: "secretstore_net", "{}: executing maintain procedures", data.self_key_pair.public());
 
-		ClusterCore::keep_alive(data.clone());
-		ClusterCore::connect_disconnected_nodes(data.clone());
-		data.sessions.stop_stalled_sessions();
+		#[cfg(test)]
+		ClusterCore::maintain_step(&data, "test hook", |data| if let Some(ref hook) = *data.maintain_hook.lock() { hook() });
+		ClusterCore::maintain_step(&data, "keep alive", |data| ClusterCore::keep_alive(data.clone()));
+		ClusterCore::maintain_step(&data, "connect", |data| ClusterCore::connect_disconnected_nodes(data.clone()));
+		ClusterCore::maintain_step(&data, "sessions cleanup", |data| {
+			for (session_id, error) in data.sessions.collect_stale(data.config.session_timeout) {
+				data.on_session_failed(&session_id, error);
+			}
+		});
+	}
+
+	/// Execute single maintain procedure. Panic is logged, so that other procedures are still executed.
+	fn maintain_step<F>(data: &Arc<ClusterData>, step: &str, f: F) where F: FnOnce(&Arc<ClusterData>) {
+		if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| f(data))) {
+			error!(target: "secretstore_net", "{}: maintain procedure '{}' has panicked: {}", data.self_key_pair.public(), step, panic_message(&panic));
+		}
 	}
 
 	/// Called for every incomming mesage.
 	fn process_connection_messages(data: Arc<ClusterData>, connection: Arc<Connection>) -> IoFuture<Result<(), Error>> {
+		let payload_data = data.clone();
+		let payload_connection = connection.clone();
 		connection
-			.read_message()
+			.read_header(data.config.max_message_size)
+			.and_then(move |(stream, header)| match header {
+				// message payload must be received within read_timeout after message header
+				Ok(header) => payload_data.deadline(payload_data.config.read_timeout, payload_connection.read_payload(stream, header)),
+				Err(err) => finished(DeadlineStatus::Meet((stream, Err(err)))).boxed(),
+			})
 			.then(move |result|
 				match result {
-					Ok((_, Ok(message))) => {
+					Ok(DeadlineStatus::Meet((_, Ok(_)))) if data.connections.is_blacklisted(connection.node_id())
+						|| !data.connections.is_known_node(connection.node_id()) => {
+						trace!(target: "secretstore_net", "{}: dropping connection to blacklisted or removed node {}", data.self_key_pair.public(), connection);
+						// stop serving connection
+						finished(Err(Error::NodeDisconnected)).boxed()
+					},
+					Ok(DeadlineStatus::Meet((_, Ok(message)))) => {
+						connection.reset_protocol_errors();
 						ClusterCore::process_connection_message(data.clone(), connection.clone(), message);
 						// continue serving connection
 						data.spawn(ClusterCore::process_connection_messages(data.clone(), connection));
 						finished(Ok(())).boxed()
 					},
-					Ok((_, Err(err))) => {
-						warn!(target: "secretstore_net", "{}: protocol error {} when reading message from node {}", data.self_key_pair.public(), err, connection.node_id());
+					Ok(DeadlineStatus::Meet((_, Err(Error::MessageTooLarge)))) => {
+						warn!(target: "secretstore_net", "{}: too large message from node {}", data.self_key_pair.public(), connection);
+						// message payload is left unread => we can't continue serving connection
+						data.connections.blacklist(connection.node_id(), data.config.blacklist_interval);
+						data.sessions.remove_queued_messages(connection.node_id());
+						data.sessions.on_connection_timeout(connection.node_id());
+						finished(Err(Error::MessageTooLarge)).boxed()
+					},
+					Ok(DeadlineStatus::Meet((_, Err(err)))) => {
+						warn!(target: "secretstore_net", "{}: protocol error {} when reading message from node {}", data.self_key_pair.public(), err, connection);
+						if connection.on_protocol_error() >= data.config.max_protocol_errors {
+							warn!(target: "secretstore_net", "{}: too many protocol errors from node {}", data.self_key_pair.public(), connection);
+							// stop serving connection
+							data.connections.remove(connection.node_id(), connection.is_inbound());
+							data.sessions.on_connection_timeout(connection.node_id());
+							return finished(Err(err)).boxed();
+						}
 						// continue serving connection
 						data.spawn(ClusterCore::process_connection_messages(data.clone(), connection));
 						finished(Err(err)).boxed()
 					},
+					Ok(DeadlineStatus::Timeout) => {
+						warn!(target: "secretstore_net", "{}: message read from node {} has stalled", data.self_key_pair.public(), connection);
+						// message payload is left unread => we can't continue serving connection
+						data.connections.remove(connection.node_id(), connection.is_inbound());
+						data.sessions.on_connection_timeout(connection.node_id());
+						finished(Err(Error::Io("message read has stalled".into()))).boxed()
+					},
 					Err(err) => {
-						warn!(target: "secretstore_net", "{}: network error {} when reading message from node {}", data.self_key_pair.public(), err, connection.node_id());
+						warn!(target: "secretstore_net", "{}: network error {} when reading message from node {}", data.self_key_pair.public(), err, connection);
 						// close connection
 						data.connections.remove(connection.node_id(), connection.is_inbound());
 						failed(err).boxed()
@@ -409,16 +1096,86 @@ impl ClusterCore {
 			).boxed()
 	}
 
+	/// Send message to the node. If sending fails, connection is closed && sessions are notified.
+	fn send_message(data: Arc<ClusterData>, connection: Arc<Connection>, message: Message) {
+		ClusterCore::send_message_attempt(data, connection, message, 0)
+	}
+
+	/// Try to send message to the node. Transient errors are retried with exponential backoff.
+	fn send_message_attempt(data: Arc<ClusterData>, connection: Arc<Connection>, message: Message, attempt: usize) {
+		let handle = data.handle.clone();
+		handle.spawn(move |handle| {
+			// deadline is set for every single message write
+			let send = deadline(data.config.send_timeout, handle, connection.send_message(message.clone()))
+				.expect("failed to create timeout");
+			ClusterCore::process_send_result(&data, connection, message, attempt, send);
+			finished(())
+		});
+	}
+
+	/// Process result of message write.
+	fn process_send_result<F>(data: &Arc<ClusterData>, connection: Arc<Connection>, message: Message, attempt: usize, send: F)
+		where F: Future<Item=DeadlineStatus<()>, Error=io::Error> + Send + 'static {
+		let send_data = data.clone();
+		let future = send
+			.then(move |result| {
+				match result {
+					Ok(DeadlineStatus::Meet(_)) => (),
+					Ok(DeadlineStatus::Timeout) => {
+						warn!(target: "secretstore_net", "{}: timeout when sending message to node {}", send_data.self_key_pair.public(), connection);
+						send_data.connections.remove(connection.node_id(), connection.is_inbound());
+						send_data.sessions.on_connection_timeout(connection.node_id());
+					},
+					Err(ref err) if is_transient_write_error(err) && attempt < send_data.config.max_write_retries => {
+						trace!(target: "secretstore_net", "{}: transient error {} when sending message to node {}. Retrying", send_data.self_key_pair.public(), err, connection);
+						let delay = write_retry_delay(send_data.config.write_retry_interval, attempt);
+						let retry_data = send_data.clone();
+						send_data.handle.spawn(move |handle| {
+							Timeout::new(delay, handle)
+								.expect("failed to create timeout")
+								.then(move |_| {
+									ClusterCore::send_message_attempt(retry_data, connection, message, attempt + 1);
+									finished(())
+								})
+						});
+					},
+					Err(err) => {
+						warn!(target: "secretstore_net", "{}: network error {} when sending message to node {}", send_data.self_key_pair.public(), err, connection);
+						send_data.connections.remove(connection.node_id(), connection.is_inbound());
+						send_data.sessions.on_connection_timeout(connection.node_id());
+					},
+				}
+				finished::<(), ()>(())
+			});
+		data.spawn(future);
+	}
+
+	/// Remove connection && let the node know that we're closing it, so that it could cleanly close it on its side.
+	fn disconnect(data: &Arc<ClusterData>, connection: Arc<Connection>) {
+		data.connections.detach(connection.node_id(), connection.is_inbound());
+
+		// connection is closed once message is sent
+		let future = connection.send_message(Message::Cluster(ClusterMessage::Disconnect(message::Disconnect {})))
+			.then(move |result| {
+				connection.close();
+				result
+			});
+		data.spawn(future);
+	}
+
 	/// Send keepalive messages to every othe node.
 	fn keep_alive(data: Arc<ClusterData>) {
 		for connection in data.connections.active_connections() {
-			let last_message_diff = time::Instant::now() - connection.last_message_time();
-			if last_message_diff > time::Duration::from_secs(KEEP_ALIVE_DISCONNECT_INTERVAL) {
-				data.connections.remove(connection.node_id(), connection.is_inbound());
+			let last_message_diff = data.clock.now() - connection.last_message_time();
+			if last_message_diff > data.config.disconnect_timeout {
+				ClusterCore::disconnect(&data, connection.clone());
 				data.sessions.on_connection_timeout(connection.node_id());
 			}
-			else if last_message_diff > time::Duration::from_secs(KEEP_ALIVE_SEND_INTERVAL) {
-				data.spawn(connection.send_message(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive {}))));
+			else if last_message_diff > data.config.keep_alive_interval {
+				let nonce = connection.next_keep_alive_nonce();
+				ClusterCore::send_message(data.clone(), connection, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive {
+					nonce: nonce,
+				})));
 			}
 		}
 	}
@@ -426,31 +1183,92 @@ impl ClusterCore {
 	/// Try to connect to every disconnected node.
 	fn connect_disconnected_nodes(data: Arc<ClusterData>) {
 		for (node_id, node_address) in data.connections.disconnected_nodes() {
-			if data.config.allow_connecting_to_higher_nodes || data.self_key_pair.public() < &node_id {
-				ClusterCore::connect(data.clone(), node_address);
-			}
+			ClusterCore::connect_node(data.clone(), &node_id, node_address);
+		}
+	}
+
+	/// Try to connect to node after given delay, unless connection is established before.
+	fn connect_node_after(data: Arc<ClusterData>, node_id: NodeId, node_address: (String, u16), delay: time::Duration) {
+		if delay == time::Duration::default() {
+			return ClusterCore::connect_node(data, &node_id, node_address);
+		}
+
+		let handle = data.handle.clone();
+		handle.spawn(move |handle| {
+			Timeout::new(delay, handle)
+				.expect("failed to create timeout")
+				.then(move |_| {
+					if !data.connections.is_connected(&node_id) {
+						ClusterCore::connect_node(data, &node_id, node_address);
+					}
+					finished(())
+				})
+		});
+	}
+
+	/// Try to connect to disconnected node.
+	fn connect_node(data: Arc<ClusterData>, node_id: &NodeId, node_address: (String, u16)) {
+		// listen-only node relies on other nodes to connect
+		if !data.config.connect_outbound || data.connections.is_blacklisted(node_id) {
+			return;
+		}
+
+		if (data.config.allow_connecting_to_higher_nodes || data.self_key_pair.public() < node_id)
+			&& data.connections.start_connecting(node_id, data.config.maintain_interval) {
+			ClusterCore::connect(data, node_id.clone(), node_address);
 		}
 	}
 
 	/// Process connection future result.
-	fn process_connection_result(data: Arc<ClusterData>, is_inbound: bool, result: Result<DeadlineStatus<Result<NetConnection, Error>>, io::Error>) -> IoFuture<Result<(), Error>> {
+	/// Failed outbound connection attempts are not retried until the reconnect interval of the node passes
+	/// (see ClusterConnections::start_connecting), so here we only need to report the failure.
+	fn process_connection_result(data: Arc<ClusterData>, is_inbound: bool, node_address: SocketAddr, result: Result<DeadlineStatus<Result<NetConnection, Error>>, io::Error>) -> IoFuture<Result<(), Error>> {
+		let direction = if is_inbound { "from" } else { "to" };
+		let attempts = data.connections.attempts(is_inbound);
 		match result {
 			Ok(DeadlineStatus::Meet(Ok(connection))) => {
-				let connection = Connection::new(is_inbound, connection);
+				attempts.connects_succeeded.fetch_add(1, Ordering::Relaxed);
+				let connection = Connection::new(is_inbound, connection, data.config.redact_node_addresses, data.clock.clone(),
+					data.config.compression_threshold);
+				let is_connection_restored = data.connections.is_connection_lost(connection.node_id());
 				if data.connections.insert(connection.clone()) {
+					if is_connection_restored {
+						attempts.connections_restored.fetch_add(1, Ordering::Relaxed);
+						data.sessions.on_connection_restored(connection.node_id());
+					}
+					data.on_connected_nodes_changed();
+					// peers, which do not announce compression support, always receive uncompressed messages
+					if connection.supports_compression() {
+						ClusterCore::send_message(data.clone(), connection.clone(), Message::Cluster(ClusterMessage::Capabilities(message::Capabilities {
+							compression: true,
+						})));
+					}
 					ClusterCore::process_connection_messages(data.clone(), connection)
 				} else {
 					finished(Ok(())).boxed()
 				}
 			},
-			Ok(DeadlineStatus::Meet(Err(_))) => {
+			Ok(DeadlineStatus::Meet(Err(Error::InvalidNodeId))) => {
+				attempts.handshakes_rejected.fetch_add(1, Ordering::Relaxed);
+				data.connections.on_handshake_failed(node_address);
+				warn!(target: "secretstore_net", "{}: handshake with unknown node {} {} has been rejected", data.self_key_pair.public(), direction, node_address);
+				finished(Ok(())).boxed()
+			},
+			Ok(DeadlineStatus::Meet(Err(err))) => {
+				attempts.connects_failed.fetch_add(1, Ordering::Relaxed);
+				data.connections.on_handshake_failed(node_address);
+				warn!(target: "secretstore_net", "{}: handshake error {} with node {} {}", data.self_key_pair.public(), err, direction, node_address);
 				finished(Ok(())).boxed()
 			},
 			Ok(DeadlineStatus::Timeout) => {
+				attempts.connects_timed_out.fetch_add(1, Ordering::Relaxed);
+				trace!(target: "secretstore_net", "{}: timeout when establishing connection {} {}", data.self_key_pair.public(), direction, node_address);
 				finished(Ok(())).boxed()
 			},
-			Err(_) => {
+			Err(err) => {
 				// network error
+				attempts.connects_failed.fetch_add(1, Ordering::Relaxed);
+				trace!(target: "secretstore_net", "{}: network error {} when establishing connection {} {}", data.self_key_pair.public(), err, direction, node_address);
 				finished(Ok(())).boxed()
 			},
 		}
@@ -458,26 +1276,60 @@ impl ClusterCore {
 
 	/// Process single message from the connection.
 	fn process_connection_message(data: Arc<ClusterData>, connection: Arc<Connection>, message: Message) {
-		connection.set_last_message_time(time::Instant::now());
-		trace!(target: "secretstore_net", "{}: received message {} from {}", data.self_key_pair.public(), message, connection.node_id());
+		// replayed (or reordered) KeepAlive must not make dead connection look alive
+		if let Message::Cluster(ClusterMessage::KeepAlive(ref keep_alive)) = message {
+			if !connection.on_keep_alive(keep_alive.nonce) {
+				warn!(target: "secretstore_net", "{}: ignoring stale keep alive {} from node {}", data.self_key_pair.public(), keep_alive.nonce, connection);
+				connection.on_message_rejected();
+				return;
+			}
+		}
+
+		connection.set_last_message_time(data.clock.now());
+		trace!(target: "secretstore_net", "{}: received message {} from {}", data.self_key_pair.public(), message.kind(), connection);
+		trace!(target: "secretstore_net_payload", "{}: received message {:?} from {}", data.self_key_pair.public(), message, connection);
+		if let Err(err) = connection.check_message_sender(&message) {
+			warn!(target: "secretstore_net", "{}: rejecting message {} from node {}: {}", data.self_key_pair.public(), message.kind(), connection, err);
+			connection.on_message_rejected();
+			return;
+		}
+
 		match message {
-			Message::Encryption(message) => ClusterCore::process_encryption_message(data, connection, message),
-			Message::Decryption(message) => ClusterCore::process_decryption_message(data, connection, message),
+			Message::Encryption(message) => ClusterCore::process_encryption_message(data, connection.node_id().clone(), message),
+			Message::Decryption(message) => ClusterCore::process_decryption_message(data, connection.node_id().clone(), message),
 			Message::Cluster(message) => ClusterCore::process_cluster_message(data, connection, message),
 		}
 	}
 
-	/// Process single encryption message from the connection.
-	fn process_encryption_message(data: Arc<ClusterData>, connection: Arc<Connection>, mut message: EncryptionMessage) {
+	/// Process single message, which this node has sent to itself.
+	fn process_self_message(data: Arc<ClusterData>, message: Message) {
+		let self_node_id = data.self_key_pair.public().clone();
+		trace!(target: "secretstore_net", "{}: received message {} from self", self_node_id, message);
+		match message {
+			Message::Encryption(message) => ClusterCore::process_encryption_message(data, self_node_id, message),
+			Message::Decryption(message) => ClusterCore::process_decryption_message(data, self_node_id, message),
+			Message::Cluster(message) => warn!(target: "secretstore_net", "{}: received unexpected message {} from self", self_node_id, message),
+		}
+	}
+
+	/// Process single encryption message from the node.
+	fn process_encryption_message(data: Arc<ClusterData>, mut sender: NodeId, mut message: EncryptionMessage) {
 		let session_id = message.session_id().clone();
-		let mut sender = connection.node_id().clone();
 		let session = match message {
 			EncryptionMessage::InitializeSession(_) => {
 				let mut connected_nodes = data.connections.connected_nodes();
 				connected_nodes.insert(data.self_key_pair.public().clone());
 
+				// session could be started on the subset of cluster nodes => only connection to master is required here
+				// connections to other participants are checked when participants set is known (on CompleteInitialization)
+				let required_nodes = vec![sender.clone(), data.self_key_pair.public().clone()].into_iter().collect();
 				let cluster = Arc::new(ClusterView::new(data.clone(), connected_nodes));
-				data.sessions.new_encryption_session(sender.clone(), session_id.clone(), cluster)
+				let session = data.check_accepting_sessions()
+					.and_then(|_| data.sessions.new_encryption_session_with_nodes(sender.clone(), session_id.clone(), cluster, &required_nodes));
+				if session.is_ok() {
+					data.on_session_started(&session_id);
+				}
+				session
 			},
 			_ => {
 				data.sessions.encryption_session(&session_id)
@@ -492,8 +1344,12 @@ impl ClusterCore {
 					session.on_initialize_session(sender.clone(), message),
 				EncryptionMessage::ConfirmInitialization(ref message) =>
 					session.on_confirm_initialization(sender.clone(), message),
-				EncryptionMessage::CompleteInitialization(ref message) =>
-					session.on_complete_initialization(sender.clone(), message),
+				EncryptionMessage::CompleteInitialization(ref message) => {
+					// session could be started on the subset of cluster nodes => do not send messages to other nodes
+					let nodes = message.nodes.keys().cloned().map(Into::into).collect();
+					data.sessions.restrict_encryption_session_nodes(&session_id, &nodes)
+						.and_then(|_| session.on_complete_initialization(sender.clone(), message))
+				},
 				EncryptionMessage::KeysDissemination(ref message) =>
 					session.on_keys_dissemination(sender.clone(), message),
 				EncryptionMessage::PublicKeyShare(ref message) =>
@@ -509,6 +1365,14 @@ impl ClusterCore {
 					let session_state = session.state();
 					if session_state == EncryptionSessionState::Finished {
 						info!(target: "secretstore_net", "{}: encryption session completed", data.self_key_pair.public());
+						data.on_session_completed(&session_id);
+						ClusterCore::broadcast_session_completed(&data, data.sessions.encryption_session_participants(&session_id), message::ClusterSessionCompleted {
+							session: session_id.clone().into(),
+							sub_session: None,
+						});
+					}
+					if session_state == EncryptionSessionState::Failed {
+						data.on_session_failed(&session_id, session.error().unwrap_or(Error::InvalidStateForRequest));
 					}
 					if session_state == EncryptionSessionState::Finished || session_state == EncryptionSessionState::Failed {
 						data.sessions.remove_encryption_session(&session_id);
@@ -534,8 +1398,12 @@ impl ClusterCore {
 					data.sessions.respond_with_encryption_error(&session_id, message::SessionError {
 						session: session_id.clone().into(),
 						error: format!("{:?}", err),
+						code: Some((&err).into()),
 					});
 					if err != Error::InvalidSessionId {
+						if session.is_ok() {
+							data.on_session_failed(&session_id, err.clone());
+						}
 						data.sessions.remove_encryption_session(&session_id);
 					}
 					break;
@@ -544,18 +1412,18 @@ impl ClusterCore {
 		}
 	}
 
-	/// Process single decryption message from the connection.
-	fn process_decryption_message(data: Arc<ClusterData>, connection: Arc<Connection>, mut message: DecryptionMessage) {
+	/// Process single decryption message from the node.
+	fn process_decryption_message(data: Arc<ClusterData>, mut sender: NodeId, mut message: DecryptionMessage) {
 		let session_id = message.session_id().clone();
 		let sub_session_id = message.sub_session_id().clone();
-		let mut sender = connection.node_id().clone();
 		let session = match message {
 			DecryptionMessage::InitializeDecryptionSession(_) => {
 				let mut connected_nodes = data.connections.connected_nodes();
 				connected_nodes.insert(data.self_key_pair.public().clone());
 
 				let cluster = Arc::new(ClusterView::new(data.clone(), connected_nodes));
-				data.sessions.new_decryption_session(sender.clone(), session_id.clone(), sub_session_id.clone(), cluster)
+				data.check_accepting_sessions()
+					.and_then(|_| data.sessions.new_decryption_session(sender.clone(), session_id.clone(), sub_session_id.clone(), cluster))
 			},
 			_ => {
 				data.sessions.decryption_session(&session_id, &sub_session_id)
@@ -585,6 +1453,10 @@ impl ClusterCore {
 					let session_state = session.state();
 					if session_state == DecryptionSessionState::Finished {
 						info!(target: "secretstore_net", "{}: decryption session completed", data.self_key_pair.public());
+						ClusterCore::broadcast_session_completed(&data, data.sessions.decryption_session_participants(&session_id, &sub_session_id), message::ClusterSessionCompleted {
+							session: session_id.clone().into(),
+							sub_session: Some(sub_session_id.clone().into()),
+						});
 					}
 					if session_state == DecryptionSessionState::Finished || session_state == DecryptionSessionState::Failed {
 						data.sessions.remove_decryption_session(&session_id, &sub_session_id);
@@ -607,13 +1479,23 @@ impl ClusterCore {
 				},
 				Err(err) => {
 					warn!(target: "secretstore_net", "{}: decryption session error {} when processing message {} from node {}", data.self_key_pair.public(), err, message, sender);
-					data.sessions.respond_with_decryption_error(&session_id, &sub_session_id, &sender, message::DecryptionSessionError {
+					let error = message::DecryptionSessionError {
 						session: session_id.clone().into(),
 						sub_session: sub_session_id.clone().into(),
 						error: format!("{:?}", err),
-					});
+					};
 					if err != Error::InvalidSessionId {
+						data.sessions.respond_with_decryption_error(&session_id, &sub_session_id, &sender, error);
 						data.sessions.remove_decryption_session(&session_id, &sub_session_id);
+					} else if !is_queued_message {
+						match message {
+							// do not respond to error messages to avoid endless errors exchange
+							DecryptionMessage::DecryptionSessionError(_) => (),
+							// there's no session to respond with => respond directly to the sender
+							_ => if let Some(connection) = data.connection(&sender) {
+								ClusterCore::send_message(data.clone(), connection, Message::Decryption(DecryptionMessage::DecryptionSessionError(error)));
+							},
+						}
 					}
 					break;
 				},
@@ -621,30 +1503,71 @@ impl ClusterCore {
 		}
 	}
 
+	/// Notify session participants that session has been completed on this (master) node.
+	fn broadcast_session_completed(data: &Arc<ClusterData>, participants: Option<(NodeId, BTreeSet<NodeId>)>, message: message::ClusterSessionCompleted) {
+		let (master, nodes) = match participants {
+			Some((ref master, ref nodes)) if master == data.self_key_pair.public() => (master.clone(), nodes.clone()),
+			_ => return,
+		};
+
+		for node in nodes.iter().filter(|n| **n != master) {
+			if let Some(connection) = data.connection(node) {
+				ClusterCore::send_message(data.clone(), connection, Message::Cluster(ClusterMessage::SessionCompleted(message.clone())));
+			}
+		}
+	}
+
 	/// Process single cluster message from the connection.
 	fn process_cluster_message(data: Arc<ClusterData>, connection: Arc<Connection>, message: ClusterMessage) {
 		match message {
-			ClusterMessage::KeepAlive(_) => data.spawn(connection.send_message(Message::Cluster(ClusterMessage::KeepAliveResponse(message::KeepAliveResponse {})))),
+			ClusterMessage::KeepAlive(_) => ClusterCore::send_message(data.clone(), connection,
+				Message::Cluster(ClusterMessage::KeepAliveResponse(message::KeepAliveResponse {}))),
 			ClusterMessage::KeepAliveResponse(_) => (),
-			_ => warn!(target: "secretstore_net", "{}: received unexpected message {} from node {} at {}", data.self_key_pair.public(), message, connection.node_id(), connection.node_address()),
+			ClusterMessage::Ping(ping) => ClusterCore::send_message(data.clone(), connection, Message::Cluster(ClusterMessage::Pong(message::Pong {
+				nonce: ping.nonce,
+			}))),
+			ClusterMessage::Pong(pong) => data.on_pong(pong.nonce),
+			ClusterMessage::Capabilities(capabilities) => connection.set_peer_supports_compression(capabilities.compression),
+			ClusterMessage::SessionCompleted(message) => {
+				let sub_session_id = message.sub_session.map(Into::into);
+				data.sessions.on_master_session_completed(connection.node_id(), &message.session, sub_session_id.as_ref());
+			},
+			ClusterMessage::Disconnect(_) => {
+				trace!(target: "secretstore_net", "{}: node {} is closing connection", data.self_key_pair.public(), connection);
+				data.connections.remove(connection.node_id(), connection.is_inbound());
+				data.sessions.on_connection_timeout(connection.node_id());
+			},
+			_ => warn!(target: "secretstore_net", "{}: received unexpected message {} from node {}", data.self_key_pair.public(), message, connection),
 		}
 	}
 }
 
 impl ClusterConnections {
 	pub fn new(config: &ClusterConfiguration) -> Result<Self, Error> {
-		let mut connections = ClusterConnections {
-			self_node_id: config.self_key_pair.public().clone(),
-			nodes: BTreeMap::new(),
-			connections: RwLock::new(BTreeMap::new()),
-		};
+		let listen_address = check_nodes_addresses(config)?;
 
-		for (node_id, &(ref node_addr, node_port)) in config.nodes.iter().filter(|&(node_id, _)| node_id != config.self_key_pair.public()) {
-			let socket_address = make_socket_address(&node_addr, node_port)?;
-			connections.nodes.insert(node_id.clone(), socket_address);
-		}
+		let nodes = config.nodes.iter()
+			.filter(|&(node_id, _)| node_id != config.self_key_pair.public())
+			.map(|(node_id, node_address)| (node_id.clone(), config.advertised_addresses.get(node_id).unwrap_or(node_address).clone()))
+			.collect();
 
-		Ok(connections)
+		Ok(ClusterConnections {
+			self_node_id: config.self_key_pair.public().clone(),
+			listen_address: listen_address,
+			nodes: RwLock::new(nodes),
+			connections: RwLock::new(BTreeMap::new()),
+			blacklisted: RwLock::new(BTreeMap::new()),
+			backoff: RwLock::new(BTreeMap::new()),
+			pending_connects: Mutex::new(BTreeSet::new()),
+			inbound_attempts: ConnectionAttemptsCounters::default(),
+			outbound_attempts: ConnectionAttemptsCounters::default(),
+			pending_accepts: AtomicUsize::new(0),
+			max_inbound_connections: config.max_inbound_connections,
+			ever_connected: RwLock::new(BTreeSet::new()),
+			events: Mutex::new(VecDeque::new()),
+			max_events: config.max_connection_events,
+			clock: config.clock.clone(),
+		})
 	}
 
 	pub fn cluster_state(&self) -> ClusterState {
@@ -658,44 +1581,201 @@ impl ClusterConnections {
 	}
 
 	pub fn insert(&self, connection: Arc<Connection>) -> bool {
+		if self.is_blacklisted(connection.node_id()) {
+			trace!(target: "secretstore_net", "{}: refusing connection to blacklisted node {}", self.self_node_id, connection);
+			return false;
+		}
+
 		let mut connections = self.connections.write();
 		if connections.contains_key(connection.node_id()) {
 			// we have already connected to the same node
-			// the agreement is that node with lower id must establish connection to node with higher id
-			if (&self.self_node_id < connection.node_id() && connection.is_inbound())
-				|| (&self.self_node_id > connection.node_id() && !connection.is_inbound()) {
+			if !should_keep_connection(&self.self_node_id, connection.node_id(), connection.is_inbound()) {
+				self.attempts(connection.is_inbound()).duplicates_dropped.fetch_add(1, Ordering::Relaxed);
 				return false;
 			}
 		}
 
-		trace!(target: "secretstore_net", "{}: inserting connection to {} at {}", self.self_node_id, connection.node_id(), connection.node_address());
+		trace!(target: "secretstore_net", "{}: inserting connection to {}", self.self_node_id, connection);
+		self.backoff.write().remove(connection.node_id());
+		self.ever_connected.write().insert(connection.node_id().clone());
+		self.add_event(ConnectionEventKind::Connected(connection.node_id().clone()));
 		connections.insert(connection.node_id().clone(), connection);
 		true
 	}
 
+	/// Check if connection to given node has been established before && is currently lost.
+	pub fn is_connection_lost(&self, node: &NodeId) -> bool {
+		self.ever_connected.read().contains(node) && !self.is_connected(node)
+	}
+
 	pub fn remove(&self, node: &NodeId, is_inbound: bool) {
+		if let Some(connection) = self.detach(node, is_inbound) {
+			connection.close();
+		}
+	}
+
+	/// Remove connection without closing it.
+	pub fn detach(&self, node: &NodeId, is_inbound: bool) -> Option<Arc<Connection>> {
 		let mut connections = self.connections.write();
 		if let Entry::Occupied(entry) = connections.entry(node.clone()) {
 			if entry.get().is_inbound() != is_inbound {
-				return;
+				return None;
 			}
 
-			trace!(target: "secretstore_net", "{}: removing connection to {} at {}", self.self_node_id, entry.get().node_id(), entry.get().node_address());
-			entry.remove_entry();
+			trace!(target: "secretstore_net", "{}: removing connection to {}", self.self_node_id, entry.get());
+			self.add_event(ConnectionEventKind::Disconnected(node.clone()));
+			return Some(entry.remove_entry().1);
+		}
+		None
+	}
+
+	pub fn add_node(&self, node: NodeId, node_address: (String, u16)) -> Result<(), Error> {
+		if node == self.self_node_id {
+			return Err(Error::InvalidNodeId);
 		}
+		check_node_address(&self.listen_address, &node, &node_address.0, node_address.1)?;
+
+		trace!(target: "secretstore_net", "{}: adding node {} at {}:{}", self.self_node_id, node, node_address.0, node_address.1);
+		self.nodes.write().insert(node, node_address);
+		Ok(())
+	}
+
+	pub fn remove_node(&self, node: &NodeId) {
+		if self.nodes.write().remove(node).is_none() {
+			return;
+		}
+
+		trace!(target: "secretstore_net", "{}: removing node {}", self.self_node_id, node);
+		self.backoff.write().remove(node);
+		if let Some(connection) = self.connections.write().remove(node) {
+			trace!(target: "secretstore_net", "{}: removing connection to removed node {}", self.self_node_id, connection);
+			self.add_event(ConnectionEventKind::Disconnected(node.clone()));
+			connection.close();
+		}
+	}
+
+	pub fn is_known_node(&self, node: &NodeId) -> bool {
+		self.nodes.read().contains_key(node)
+	}
+
+	pub fn blacklist(&self, node: &NodeId, interval: time::Duration) {
+		self.blacklisted.write().insert(node.clone(), self.clock.now() + interval);
+		self.add_event(ConnectionEventKind::Blacklisted(node.clone()));
+		if let Some(connection) = self.connections.write().remove(node) {
+			trace!(target: "secretstore_net", "{}: removing connection to blacklisted node {}", self.self_node_id, connection);
+			self.add_event(ConnectionEventKind::Disconnected(node.clone()));
+			connection.close();
+		}
+	}
+
+	/// Remember that handshake with node at given address has failed.
+	pub fn on_handshake_failed(&self, node_address: SocketAddr) {
+		self.add_event(ConnectionEventKind::HandshakeFailed(node_address));
+	}
+
+	/// Get up to `limit` most recent connection events, oldest first.
+	pub fn recent_events(&self, limit: usize) -> Vec<ConnectionEvent> {
+		let events = self.events.lock();
+		events.iter().skip(events.len().saturating_sub(limit)).cloned().collect()
+	}
+
+	fn add_event(&self, kind: ConnectionEventKind) {
+		let mut events = self.events.lock();
+		if self.max_events == 0 {
+			return;
+		}
+		if events.len() == self.max_events {
+			events.pop_front();
+		}
+		events.push_back(ConnectionEvent {
+			time: self.clock.now(),
+			kind: kind,
+		});
+	}
+
+	pub fn start_connecting(&self, node: &NodeId, initial_interval: time::Duration) -> bool {
+		// do not start new connection attempt until previous one is completed
+		let mut pending_connects = self.pending_connects.lock();
+		if pending_connects.contains(node) {
+			return false;
+		}
+
+		let now = self.clock.now();
+		let mut backoff = self.backoff.write();
+		let interval = match backoff.get(node) {
+			Some(&(next_attempt_time, _)) if next_attempt_time > now => return false,
+			Some(&(_, interval)) => cmp::min(interval * 2, time::Duration::from_secs(MAX_RECONNECT_INTERVAL)),
+			None => initial_interval,
+		};
+
+		// randomize next attempt time so that nodes are not reconnecting simultaneously
+		backoff.insert(node.clone(), (now + interval - random_jitter(interval), interval));
+		pending_connects.insert(node.clone());
+		true
+	}
+
+	pub fn finish_connecting(&self, node: &NodeId) {
+		self.pending_connects.lock().remove(node);
+	}
+
+	/// Start accepting inbound connection. Returns false if inbound connections limit is reached.
+	pub fn start_accepting(&self) -> bool {
+		if self.inbound_connections_count() >= self.max_inbound_connections {
+			return false;
+		}
+
+		self.pending_accepts.fetch_add(1, Ordering::SeqCst);
+		true
+	}
+
+	pub fn finish_accepting(&self) {
+		self.pending_accepts.fetch_sub(1, Ordering::SeqCst);
+	}
+
+	/// Number of inbound connections, including connections with handshake in progress.
+	pub fn inbound_connections_count(&self) -> usize {
+		self.pending_accepts.load(Ordering::SeqCst)
+			+ self.connections.read().values().filter(|c| c.is_inbound()).count()
+	}
+
+	pub fn is_blacklisted(&self, node: &NodeId) -> bool {
+		self.blacklisted.read().get(node)
+			.map(|blacklisted_until| *blacklisted_until > self.clock.now())
+			.unwrap_or(false)
+	}
+
+	pub fn is_connected(&self, node: &NodeId) -> bool {
+		self.connections.read().contains_key(node)
 	}
 
 	pub fn connected_nodes(&self) -> BTreeSet<NodeId> {
 		self.connections.read().keys().cloned().collect()
 	}
 
+	pub fn connection_stats(&self) -> BTreeMap<NodeId, ConnectionStats> {
+		self.connections.read().iter()
+			.map(|(node_id, connection)| (node_id.clone(), connection.stats()))
+			.collect()
+	}
+
+	pub fn connection_metrics(&self) -> ConnectionMetrics {
+		ConnectionMetrics {
+			inbound: self.inbound_attempts.snapshot(),
+			outbound: self.outbound_attempts.snapshot(),
+		}
+	}
+
+	fn attempts(&self, is_inbound: bool) -> &ConnectionAttemptsCounters {
+		if is_inbound { &self.inbound_attempts } else { &self.outbound_attempts }
+	}
+
 	pub fn active_connections(&self)-> Vec<Arc<Connection>> {
 		self.connections.read().values().cloned().collect()
 	}
 
-	pub fn disconnected_nodes(&self) -> BTreeMap<NodeId, SocketAddr> {
+	pub fn disconnected_nodes(&self) -> BTreeMap<NodeId, (String, u16)> {
 		let connections = self.connections.read();
-		self.nodes.iter()
+		self.nodes.read().iter()
 			.filter(|&(node_id, _)| !connections.contains_key(node_id))
 			.map(|(node_id, node_address)| (node_id.clone(), node_address.clone()))
 			.collect()
@@ -706,17 +1786,31 @@ impl ClusterSessions {
 	pub fn new(config: &ClusterConfiguration) -> Self {
 		ClusterSessions {
 			self_node_id: config.self_key_pair.public().clone(),
-			nodes: config.nodes.keys().cloned().collect(),
+			nodes: RwLock::new(config.nodes.keys().cloned().collect()),
 			acl_storage: config.acl_storage.clone(),
 			key_storage: config.key_storage.clone(),
 			encryption_sessions: RwLock::new(BTreeMap::new()),
 			decryption_sessions: RwLock::new(BTreeMap::new()),
+			max_sessions: config.max_sessions,
 			make_faulty_encryption_sessions: AtomicBool::new(false),
+			clock: config.clock.clone(),
 		}
 	}
 
 	pub fn new_encryption_session(&self, master: NodeId, session_id: SessionId, cluster: Arc<ClusterView>) -> Result<Arc<EncryptionSessionImpl>, Error> {
+		// communicating to all other nodes is crucial for encryption session
+		// => check that we have connections to all cluster nodes
+		let nodes = self.nodes.read().clone();
+		self.new_encryption_session_with_nodes(master, session_id, cluster, &nodes)
+	}
+
+	/// Create new encryption session, which requires connections to given nodes only.
+	pub fn new_encryption_session_with_nodes(&self, master: NodeId, session_id: SessionId, cluster: Arc<ClusterView>, nodes: &BTreeSet<NodeId>) -> Result<Arc<EncryptionSessionImpl>, Error> {
+		// sessions limit is checked under the same lock, which is used to insert the session
+		// lock order: encryption sessions first, decryption sessions next
 		let mut encryption_sessions = self.encryption_sessions.write();
+		self.check_sessions_limit(encryption_sessions.len() + self.decryption_sessions.read().len())?;
+
 		// check that there's no active encryption session with the same id
 		if encryption_sessions.contains_key(&session_id) {
 			return Err(Error::DuplicateSessionId);
@@ -726,9 +1820,7 @@ impl ClusterSessions {
 			return Err(Error::DuplicateSessionId);
 		}
 
-		// communicating to all other nodes is crucial for encryption session
-		// => check that we have connections to all cluster nodes
-		if self.nodes.iter().any(|n| !cluster.is_connected(n)) {
+		if nodes.iter().any(|n| !cluster.is_connected(n)) {
 			return Err(Error::NodeDisconnected);
 		}
 
@@ -741,7 +1833,8 @@ impl ClusterSessions {
 		let encryption_session = QueuedEncryptionSession {
 			master: master,
 			cluster_view: cluster,
-			last_message_time: time::Instant::now(),
+			creation_time: self.clock.now(),
+			last_message_time: self.clock.now(),
 			session: session.clone(),
 			queue: VecDeque::new()
 		};
@@ -752,12 +1845,51 @@ impl ClusterSessions {
 		Ok(session)
 	}
 
-	pub fn remove_encryption_session(&self, session_id: &SessionId) {
-		self.encryption_sessions.write().remove(session_id);
+	fn check_sessions_limit(&self, active_sessions_count: usize) -> Result<(), Error> {
+		if active_sessions_count >= self.max_sessions {
+			return Err(Error::TooManySessions);
+		}
+		Ok(())
 	}
 
-	pub fn encryption_session(&self, session_id: &SessionId) -> Option<Arc<EncryptionSessionImpl>> {
-		self.encryption_sessions.read().get(session_id).map(|s| s.session.clone())
+	pub fn active_encryption_sessions(&self) -> Vec<SessionId> {
+		self.encryption_sessions.read().keys().cloned().collect()
+	}
+
+	pub fn active_decryption_sessions(&self) -> Vec<DecryptionSessionId> {
+		self.decryption_sessions.read().keys().cloned().collect()
+	}
+
+	pub fn remove_encryption_session(&self, session_id: &SessionId) {
+		self.encryption_sessions.write().remove(session_id);
+	}
+
+	pub fn encryption_session(&self, session_id: &SessionId) -> Option<Arc<EncryptionSessionImpl>> {
+		self.encryption_sessions.read().get(session_id).map(|s| s.session.clone())
+	}
+
+	/// Get parameters of active encryption session.
+	pub fn encryption_session_info(&self, session_id: &SessionId) -> Option<SessionInfo> {
+		let encryption_sessions = self.encryption_sessions.read();
+		let session = match encryption_sessions.get(session_id) {
+			Some(session) => session,
+			None => return None,
+		};
+
+		// threshold && nodes set are filled once, when session is initialized
+		session.session.threshold().map(|threshold| SessionInfo {
+			master: session.master.clone(),
+			threshold: threshold,
+			nodes: session.session.nodes(),
+		})
+	}
+
+	/// Restrict nodes, participating in encryption session. Fails if this node is not connected to some of given nodes.
+	pub fn restrict_encryption_session_nodes(&self, session_id: &SessionId, nodes: &BTreeSet<NodeId>) -> Result<(), Error> {
+		match self.encryption_sessions.read().get(session_id) {
+			Some(session) => session.cluster_view.restrict_nodes(nodes),
+			None => Err(Error::InvalidSessionId),
+		}
 	}
 
 	pub fn enqueue_encryption_message(&self, session_id: &SessionId, sender: NodeId, message: EncryptionMessage, is_queued_message: bool) {
@@ -782,13 +1914,35 @@ impl ClusterSessions {
 			});
 	}
 
+	pub fn abort_encryption_session(&self, session_id: &SessionId) -> Result<(), Error> {
+		let session = self.encryption_sessions.write().remove(session_id).ok_or(Error::InvalidSessionId)?;
+		let error = message::SessionError {
+			session: session_id.clone().into(),
+			error: "session is aborted".into(),
+			code: Some(message::SessionErrorCode::Other),
+		};
+
+		// wake up local waiters && let other participants know that session won't complete
+		// do not bother processing send error, as session is already removed
+		let _ = session.session.on_session_error(self.self_node_id.clone(), &error);
+		let _ = session.cluster_view.broadcast(Message::Encryption(EncryptionMessage::SessionError(error)));
+		Ok(())
+	}
+
 	#[cfg(test)]
 	pub fn make_faulty_encryption_sessions(&self) {
 		self.make_faulty_encryption_sessions.store(true, Ordering::Relaxed);
 	}
 
 	pub fn new_decryption_session(&self, master: NodeId, session_id: SessionId, sub_session_id: Secret, cluster: Arc<ClusterView>) -> Result<Arc<DecryptionSessionImpl>, Error> {
+		// sessions limit is checked under the same lock, which is used to insert the session
+		// lock order: encryption sessions first, decryption sessions next
+		let encryption_sessions = self.encryption_sessions.read();
 		let mut decryption_sessions = self.decryption_sessions.write();
+		self.check_sessions_limit(encryption_sessions.len() + decryption_sessions.len())?;
+		// new encryption sessions can't be inserted until decryption sessions lock is released
+		drop(encryption_sessions);
+
 		let session_id = DecryptionSessionId::new(session_id, sub_session_id);
 		if decryption_sessions.contains_key(&session_id) {
 			return Err(Error::DuplicateSessionId);
@@ -796,7 +1950,7 @@ impl ClusterSessions {
 
 		// some of nodes, which were encrypting secret may be down
 		// => do not use these in decryption session
-		let mut encrypted_data = self.key_storage.get(&session_id.id).map_err(|e| Error::KeyStorage(e.into()))?;
+		let mut encrypted_data = self.key_storage.get(session_id.session_id()).map_err(|e| Error::KeyStorage(e.into()))?;
 		let disconnected_nodes: BTreeSet<_> = encrypted_data.id_numbers.keys().cloned().collect();
 		let disconnected_nodes: BTreeSet<_> = disconnected_nodes.difference(&cluster.nodes()).cloned().collect();
 		for disconnected_node in disconnected_nodes {
@@ -804,8 +1958,8 @@ impl ClusterSessions {
 		}
 
 		let session = Arc::new(DecryptionSessionImpl::new(DecryptionSessionParams {
-			id: session_id.id.clone(),
-			access_key: session_id.access_key.clone(),
+			id: session_id.session_id().clone(),
+			access_key: session_id.sub_session_id().clone(),
 			self_node_id: self.self_node_id.clone(),
 			encrypted_data: encrypted_data,
 			acl_storage: self.acl_storage.clone(),
@@ -814,7 +1968,8 @@ impl ClusterSessions {
 		let decryption_session = QueuedDecryptionSession {
 			master: master,
 			cluster_view: cluster,
-			last_message_time: time::Instant::now(),
+			creation_time: self.clock.now(),
+			last_message_time: self.clock.now(),
 			session: session.clone(),
 			queue: VecDeque::new()
 		};
@@ -822,6 +1977,38 @@ impl ClusterSessions {
 		Ok(session)
 	}
 
+	/// Get master && participants of encryption session.
+	pub fn encryption_session_participants(&self, session_id: &SessionId) -> Option<(NodeId, BTreeSet<NodeId>)> {
+		self.encryption_sessions.read().get(session_id)
+			.map(|session| (session.master.clone(), session.cluster_view.nodes()))
+	}
+
+	/// Get master && participants of decryption session.
+	pub fn decryption_session_participants(&self, session_id: &SessionId, sub_session_id: &Secret) -> Option<(NodeId, BTreeSet<NodeId>)> {
+		let session_id = DecryptionSessionId::new(session_id.clone(), sub_session_id.clone());
+		self.decryption_sessions.read().get(&session_id)
+			.map(|session| (session.master.clone(), session.cluster_view.nodes()))
+	}
+
+	/// When session master reports that session is completed. Session is removed only if it has been started by this master.
+	pub fn on_master_session_completed(&self, master: &NodeId, session_id: &SessionId, sub_session_id: Option<&Secret>) {
+		match sub_session_id {
+			None => {
+				let mut encryption_sessions = self.encryption_sessions.write();
+				if encryption_sessions.get(session_id).map(|session| &session.master == master).unwrap_or(false) {
+					encryption_sessions.remove(session_id);
+				}
+			},
+			Some(sub_session_id) => {
+				let session_id = DecryptionSessionId::new(session_id.clone(), sub_session_id.clone());
+				let mut decryption_sessions = self.decryption_sessions.write();
+				if decryption_sessions.get(&session_id).map(|session| &session.master == master).unwrap_or(false) {
+					decryption_sessions.remove(&session_id);
+				}
+			},
+		}
+	}
+
 	pub fn remove_decryption_session(&self, session_id: &SessionId, sub_session_id: &Secret) {
 		let session_id = DecryptionSessionId::new(session_id.clone(), sub_session_id.clone());
 		self.decryption_sessions.write().remove(&session_id);
@@ -862,88 +2049,434 @@ impl ClusterSessions {
 			});
 	}
 
-	fn stop_stalled_sessions(&self) {
-		{
-			let sessions = self.encryption_sessions.write();
-			for sid in sessions.keys().collect::<Vec<_>>() {
-				let session = sessions.get(&sid).expect("enumerating only existing sessions; qed");
-				if time::Instant::now() - session.last_message_time > time::Duration::from_secs(ENCRYPTION_SESSION_TIMEOUT_INTERVAL) {
-					session.session.on_session_timeout();
-					if session.session.state() == EncryptionSessionState::Finished
-						|| session.session.state() == EncryptionSessionState::Failed {
-						self.remove_encryption_session(&sid);
-					}
-				}
+	pub fn abort_decryption_session(&self, session_id: &DecryptionSessionId) -> Result<(), Error> {
+		let session = self.decryption_sessions.write().remove(session_id).ok_or(Error::InvalidSessionId)?;
+		let error = message::DecryptionSessionError {
+			session: session_id.session_id().clone().into(),
+			sub_session: session_id.sub_session_id().clone().into(),
+			error: "session is aborted".into(),
+		};
+
+		// wake up local waiters && let other participants know that session won't complete
+		// do not bother processing send error, as session is already removed
+		let _ = session.session.on_session_error(self.self_node_id.clone(), &error);
+		let _ = session.cluster_view.broadcast(Message::Decryption(DecryptionMessage::DecryptionSessionError(error)));
+		Ok(())
+	}
+
+	pub fn remove_queued_messages(&self, node_id: &NodeId) {
+		for session in self.encryption_sessions.write().values_mut() {
+			session.queue.retain(|&(ref sender, _)| sender != node_id);
+		}
+		for session in self.decryption_sessions.write().values_mut() {
+			session.queue.retain(|&(ref sender, _)| sender != node_id);
+		}
+	}
+
+	/// Notify encryption session about timeout && remove it, if it has been stopped.
+	pub fn on_encryption_session_timeout(&self, session_id: &SessionId) {
+		let session = match self.encryption_session(session_id) {
+			Some(session) => session,
+			None => return,
+		};
+
+		session.on_session_timeout();
+		if session.state() == EncryptionSessionState::Finished
+			|| session.state() == EncryptionSessionState::Failed {
+			self.remove_encryption_session(session_id);
+		}
+	}
+
+	pub fn active_sessions_count(&self) -> usize {
+		self.encryption_sessions.read().len() + self.decryption_sessions.read().len()
+	}
+
+	pub fn oldest_session_creation_time(&self) -> Option<time::Instant> {
+		let oldest_encryption_session = self.encryption_sessions.read().values().map(|s| s.creation_time).min();
+		let oldest_decryption_session = self.decryption_sessions.read().values().map(|s| s.creation_time).min();
+		match (oldest_encryption_session, oldest_decryption_session) {
+			(Some(e), Some(d)) => Some(cmp::min(e, d)),
+			(e, d) => e.or(d),
+		}
+	}
+
+	/// Report on every active session.
+	pub fn session_report(&self) -> Vec<SessionReport> {
+		// only clone sessions references under lock, session state is read after lock is released
+		let encryption_sessions: Vec<_> = self.encryption_sessions.read().iter()
+			.map(|(id, s)| (id.clone(), s.creation_time, s.session.clone(), s.cluster_view.clone()))
+			.collect();
+		let decryption_sessions: Vec<_> = self.decryption_sessions.read().iter()
+			.map(|(id, s)| (id.clone(), s.creation_time, s.session.clone(), s.cluster_view.clone()))
+			.collect();
+
+		let mut report = Vec::with_capacity(encryption_sessions.len() + decryption_sessions.len());
+		for (id, creation_time, session, cluster_view) in encryption_sessions {
+			report.push(SessionReport {
+				id: id,
+				sub_session_id: None,
+				kind: SessionKind::Encryption,
+				creation_time: creation_time,
+				state: format!("{:?}", session.state()),
+				participants: cluster_view.nodes().len(),
+			});
+		}
+		for (id, creation_time, session, cluster_view) in decryption_sessions {
+			report.push(SessionReport {
+				id: id.session_id().clone(),
+				sub_session_id: Some(id.sub_session_id().clone()),
+				kind: SessionKind::Decryption,
+				creation_time: creation_time,
+				state: format!("{:?}", session.state()),
+				participants: cluster_view.nodes().len(),
+			});
+		}
+		report
+	}
+
+	/// Stop && remove all sessions, which are active for longer than max_age. Sessions, which have not received
+	/// messages for a while (stalled sessions), are notified about timeout && removed if they have been stopped.
+	/// Returns removed encryption sessions along with the errors they have been stopped with.
+	pub fn collect_stale(&self, max_age: time::Duration) -> Vec<(SessionId, Error)> {
+		let now = self.clock.now();
+		// do not hold sessions lock while notifying sessions, as sessions could be removed during notification
+		let encryption_stall_interval = time::Duration::from_secs(ENCRYPTION_SESSION_TIMEOUT_INTERVAL);
+		let encryption_sessions: Vec<_> = self.encryption_sessions.read().iter()
+			.filter(|&(_, session)| now - session.creation_time > max_age || now - session.last_message_time > encryption_stall_interval)
+			.map(|(sid, session)| (sid.clone(), session.session.clone(), now - session.creation_time > max_age))
+			.collect();
+		let mut removed_encryption_sessions = Vec::new();
+		for (sid, session, is_stale) in encryption_sessions {
+			warn!(target: "secretstore_net", "{}: {} encryption session {}", self.self_node_id, if is_stale { "removing stale" } else { "stopping stalled" }, sid);
+			session.on_session_timeout();
+			if is_stale || session.state() == EncryptionSessionState::Finished || session.state() == EncryptionSessionState::Failed {
+				self.remove_encryption_session(&sid);
+				removed_encryption_sessions.push((sid, session.error().unwrap_or(Error::NodeDisconnected)));
 			}
 		}
-		{
-			let sessions = self.decryption_sessions.write();
-			for sid in sessions.keys().collect::<Vec<_>>() {
-				let session = sessions.get(&sid).expect("enumerating only existing sessions; qed");
-				if time::Instant::now() - session.last_message_time > time::Duration::from_secs(DECRYPTION_SESSION_TIMEOUT_INTERVAL) {
-					session.session.on_session_timeout();
-					if session.session.state() == DecryptionSessionState::Finished
-						|| session.session.state() == DecryptionSessionState::Failed {
-						self.remove_decryption_session(&sid.id, &sid.access_key);
-					}
-				}
+
+		let decryption_stall_interval = time::Duration::from_secs(DECRYPTION_SESSION_TIMEOUT_INTERVAL);
+		let decryption_sessions: Vec<_> = self.decryption_sessions.read().iter()
+			.filter(|&(_, session)| now - session.creation_time > max_age || now - session.last_message_time > decryption_stall_interval)
+			.map(|(sid, session)| (sid.clone(), session.session.clone(), now - session.creation_time > max_age))
+			.collect();
+		for (sid, session, is_stale) in decryption_sessions {
+			warn!(target: "secretstore_net", "{}: {} decryption session {}", self.self_node_id, if is_stale { "removing stale" } else { "stopping stalled" }, sid.session_id());
+			session.on_session_timeout();
+			if is_stale || session.state() == DecryptionSessionState::Finished || session.state() == DecryptionSessionState::Failed {
+				self.remove_decryption_session(sid.session_id(), sid.sub_session_id());
 			}
 		}
+		removed_encryption_sessions
+	}
+
+	/// When connection to the node is restored after it has been lost.
+	pub fn on_connection_restored(&self, node_id: &NodeId) {
+		// sessions, which are waiting for messages from this node, have a chance to complete now
+		// => do not consider them stalled
+		let now = self.clock.now();
+		for session in self.encryption_sessions.write().values_mut().filter(|s| s.cluster_view.nodes().contains(node_id)) {
+			session.last_message_time = now;
+		}
+		for session in self.decryption_sessions.write().values_mut().filter(|s| s.cluster_view.nodes().contains(node_id)) {
+			session.last_message_time = now;
+		}
 	}
 
 	pub fn on_connection_timeout(&self, node_id: &NodeId) {
-		for (sid, session) in self.encryption_sessions.read().iter() {
-			session.session.on_node_timeout(node_id);
-			if session.session.state() == EncryptionSessionState::Finished
-				|| session.session.state() == EncryptionSessionState::Failed {
-				self.remove_encryption_session(sid);
+		// do not hold sessions lock while notifying sessions, as sessions could be removed during notification
+		let encryption_sessions: Vec<_> = self.encryption_sessions.read().iter()
+			.map(|(sid, session)| (sid.clone(), session.session.clone()))
+			.collect();
+		for (sid, session) in encryption_sessions {
+			session.on_node_timeout(node_id);
+			if session.state() == EncryptionSessionState::Finished
+				|| session.state() == EncryptionSessionState::Failed {
+				self.remove_encryption_session(&sid);
 			}
 		}
-		for (sid, session) in self.decryption_sessions.read().iter() {
-			session.session.on_node_timeout(node_id);
-			if session.session.state() == DecryptionSessionState::Finished
-				|| session.session.state() == DecryptionSessionState::Failed {
-				self.remove_decryption_session(&sid.id, &sid.access_key);
+		let decryption_sessions: Vec<_> = self.decryption_sessions.read().iter()
+			.map(|(sid, session)| (sid.clone(), session.session.clone()))
+			.collect();
+		for (sid, session) in decryption_sessions {
+			session.on_node_timeout(node_id);
+			if session.state() == DecryptionSessionState::Finished
+				|| session.state() == DecryptionSessionState::Failed {
+				self.remove_decryption_session(sid.session_id(), sid.sub_session_id());
 			}
 		}
 	}
 }
 
 impl ClusterData {
-	pub fn new(handle: &Handle, config: ClusterConfiguration, connections: ClusterConnections, sessions: ClusterSessions) -> Arc<Self> {
+	pub fn new(handle: &Handle, config: ClusterConfiguration, pool: CpuPool, connections: ClusterConnections, sessions: ClusterSessions) -> Arc<Self> {
 		Arc::new(ClusterData {
 			handle: handle.remote().clone(),
-			pool: CpuPool::new(config.threads),
+			pool: pool,
 			self_key_pair: config.self_key_pair.clone(),
 			connections: connections,
 			sessions: sessions,
+			event_sink: RwLock::new(None),
+			pending_pings: Mutex::new(BTreeMap::new()),
+			quorum_waiters: Mutex::new(BTreeMap::new()),
+			accepting_sessions: AtomicBool::new(true),
+			nodes_indices: config.nodes_order.iter().enumerate().rev().map(|(index, node)| (node.clone(), index)).collect(),
+			clock: config.clock.clone(),
+			#[cfg(test)]
+			maintain_hook: Mutex::new(None),
 			config: config,
 		})
 	}
 
+	/// Check that new sessions could be started on this node.
+	pub fn check_accepting_sessions(&self) -> Result<(), Error> {
+		if !self.accepting_sessions.load(Ordering::Relaxed) {
+			return Err(Error::NotAcceptingSessions);
+		}
+		Ok(())
+	}
+
+	/// Notify events listener that encryption session has started.
+	pub fn on_session_started(&self, session_id: &SessionId) {
+		if let Some(ref event_sink) = *self.event_sink.read() {
+			event_sink.on_session_started(session_id.clone());
+		}
+	}
+
+	/// Notify events listener that encryption session has completed.
+	pub fn on_session_completed(&self, session_id: &SessionId) {
+		if let Some(ref event_sink) = *self.event_sink.read() {
+			event_sink.on_session_completed(session_id.clone());
+		}
+	}
+
+	/// Notify events listener that encryption session has failed.
+	pub fn on_session_failed(&self, session_id: &SessionId, error: Error) {
+		if let Some(ref event_sink) = *self.event_sink.read() {
+			event_sink.on_session_failed(session_id.clone(), error);
+		}
+	}
+
+	/// Sort nodes using configured nodes order.
+	pub fn ordered_nodes<'a, I>(&self, nodes: I) -> Vec<NodeId> where I: Iterator<Item=&'a NodeId> {
+		let mut nodes: Vec<_> = nodes.cloned().collect();
+		nodes.sort_by(|n1, n2| {
+			let index1 = self.nodes_indices.get(n1).cloned().unwrap_or(usize::max_value());
+			let index2 = self.nodes_indices.get(n2).cloned().unwrap_or(usize::max_value());
+			(index1, n1).cmp(&(index2, n2))
+		});
+		nodes
+	}
+
 	/// Get connection to given node.
 	pub fn connection(&self, node: &NodeId) -> Option<Arc<Connection>> {
 		self.connections.get(node)
 	}
 
+	/// Schedule encryption session timeout. If session is still active after given interval, it is notified && stopped.
+	pub fn register_session_timeout(data: &Arc<ClusterData>, session_id: SessionId, after: time::Duration) {
+		// timeout should not keep cluster alive
+		let data = Arc::downgrade(data);
+		let handle = match data.upgrade() {
+			Some(cluster) => cluster.handle.clone(),
+			None => return,
+		};
+		handle.spawn(move |handle| {
+			Timeout::new(after, handle)
+				.expect("failed to create timeout")
+				.then(move |_| {
+					if let Some(data) = data.upgrade() {
+						data.sessions.on_encryption_session_timeout(&session_id);
+					}
+					finished(())
+				})
+		});
+	}
+
+	/// Send Ping message to the node. Returned future is resolved with round-trip time when Pong message is received.
+	pub fn ping(data: &Arc<ClusterData>, node: &NodeId) -> BoxFuture<time::Duration, Error> {
+		let connection = match data.connection(node) {
+			Some(connection) => connection,
+			None => return failed(Error::NodeDisconnected).boxed(),
+		};
+
+		let nonce = rand::thread_rng().gen();
+		let (complete, completion) = futures::oneshot();
+		data.pending_pings.lock().insert(nonce, (data.clock.now(), complete));
+		ClusterCore::send_message(data.clone(), connection, Message::Cluster(ClusterMessage::Ping(message::Ping {
+			nonce: nonce,
+		})));
+
+		// ping fails when there's no response within ping_timeout
+		let ping_timeout = data.config.ping_timeout;
+		let weak_data = Arc::downgrade(data);
+		data.handle.spawn(move |handle| {
+			Timeout::new(ping_timeout, handle)
+				.expect("failed to create timeout")
+				.then(move |_| {
+					if let Some(data) = weak_data.upgrade() {
+						if let Some((_, complete)) = data.pending_pings.lock().remove(&nonce) {
+							let _ = complete.send(Err(Error::PingTimeout));
+						}
+					}
+					finished(())
+				})
+		});
+
+		completion
+			.then(|result| result.unwrap_or(Err(Error::NodeDisconnected)))
+			.boxed()
+	}
+
+	/// Wait until at least min_nodes other nodes are connected.
+	pub fn wait_for_quorum(data: &Arc<ClusterData>, min_nodes: usize, timeout: time::Duration) -> BoxFuture<(), Error> {
+		let (complete, completion) = futures::oneshot();
+		let waiter_id = rand::thread_rng().gen();
+		{
+			// check under lock, so that connection, inserted right after the check, notifies this waiter
+			let mut quorum_waiters = data.quorum_waiters.lock();
+			if data.connections.connected_nodes().len() >= min_nodes {
+				return finished(()).boxed();
+			}
+			quorum_waiters.insert(waiter_id, (min_nodes, complete));
+		}
+
+		// waiter is cancelled when quorum is not reached within timeout
+		let weak_data = Arc::downgrade(data);
+		data.handle.spawn(move |handle| {
+			Timeout::new(timeout, handle)
+				.expect("failed to create timeout")
+				.then(move |_| {
+					if let Some(data) = weak_data.upgrade() {
+						if let Some((_, complete)) = data.quorum_waiters.lock().remove(&waiter_id) {
+							let _ = complete.send(Err(Error::QuorumTimeout));
+						}
+					}
+					finished(())
+				})
+		});
+
+		completion
+			.then(|result| result.unwrap_or(Err(Error::QuorumTimeout)))
+			.boxed()
+	}
+
+	/// Called when new connection is established. Notifies all waiters, which have reached their quorum.
+	pub fn on_connected_nodes_changed(&self) {
+		let mut quorum_waiters = self.quorum_waiters.lock();
+		let connected_nodes_count = self.connections.connected_nodes().len();
+		let satisfied_waiters: Vec<_> = quorum_waiters.iter()
+			.filter(|&(_, &(min_nodes, _))| connected_nodes_count >= min_nodes)
+			.map(|(waiter_id, _)| *waiter_id)
+			.collect();
+		for waiter_id in satisfied_waiters {
+			if let Some((_, complete)) = quorum_waiters.remove(&waiter_id) {
+				let _ = complete.send(Ok(()));
+			}
+		}
+	}
+
+	/// Called when Pong message is received.
+	pub fn on_pong(&self, nonce: u64) {
+		if let Some((sent_time, complete)) = self.pending_pings.lock().remove(&nonce) {
+			let _ = complete.send(Ok(self.clock.now() - sent_time));
+		}
+	}
+
 	/// Spawns a future using thread pool and schedules execution of it with event loop handle.
-	pub fn spawn<F>(&self, f: F) where F: Future + Send + 'static, F::Item: Send + 'static, F::Error: Send + 'static {
-		let pool_work = self.pool.spawn(f);
+	/// Errors and panics of the future are logged, but otherwise ignored. Errors are expected
+	/// (e.g. when connection is closed), so only panics are logged as errors.
+	pub fn spawn<F>(&self, f: F) where F: Future + Send + 'static, F::Item: Send + 'static, F::Error: fmt::Debug + Send + 'static {
+		let self_node_id = self.self_key_pair.public().clone();
+		let pool_work = self.pool.spawn(AssertUnwindSafe(f).catch_unwind());
 		self.handle.spawn(move |_handle| {
-			pool_work.then(|_| finished(()))
+			pool_work.then(move |result| {
+				match result {
+					Ok(Ok(_)) => (),
+					Ok(Err(err)) => warn!(target: "secretstore_net", "{}: spawned future has failed with error {:?}", self_node_id, err),
+					Err(panic) => error!(target: "secretstore_net", "{}: spawned future has panicked: {}", self_node_id, panic_message(&panic)),
+				}
+				finished(())
+			})
 		})
 	}
+
+	/// Complete a passed future or fail if it is not completed within timeout.
+	/// Timeout is created on the event loop, so returned future could be polled from any thread.
+	pub fn deadline<F, T>(&self, duration: time::Duration, future: F) -> IoFuture<DeadlineStatus<T>>
+		where F: Future<Item = T, Error = io::Error> + Send + 'static, T: Send + 'static {
+		let (complete, completion) = futures::oneshot();
+		self.handle.spawn(move |handle| {
+			let _ = complete.send(deadline(duration, handle, future));
+			finished(())
+		});
+
+		completion
+			.then(|result| match result {
+				Ok(Ok(deadline)) => deadline.boxed(),
+				Ok(Err(err)) => failed(err).boxed(),
+				Err(_) => failed(io::Error::new(io::ErrorKind::Other, "event loop has been stopped")).boxed(),
+			})
+			.boxed()
+	}
+}
+
+impl ConnectionAttemptsCounters {
+	pub fn snapshot(&self) -> ConnectionAttempts {
+		ConnectionAttempts {
+			connects_succeeded: self.connects_succeeded.load(Ordering::Relaxed),
+			connects_failed: self.connects_failed.load(Ordering::Relaxed),
+			connects_timed_out: self.connects_timed_out.load(Ordering::Relaxed),
+			handshakes_rejected: self.handshakes_rejected.load(Ordering::Relaxed),
+			duplicates_dropped: self.duplicates_dropped.load(Ordering::Relaxed),
+			connections_restored: self.connections_restored.load(Ordering::Relaxed),
+		}
+	}
 }
 
 impl Connection {
-	pub fn new(is_inbound: bool, connection: NetConnection) -> Arc<Connection> {
+	pub fn new(is_inbound: bool, connection: NetConnection, redact_address: bool, clock: Arc<Clock>, compression_threshold: Option<usize>) -> Arc<Connection> {
+		let now = clock.now();
 		Arc::new(Connection {
 			node_id: connection.node_id,
 			node_address: connection.address,
 			is_inbound: is_inbound,
+			redact_address: redact_address,
 			stream: connection.stream,
 			key: connection.key,
-			last_message_time: Mutex::new(time::Instant::now()),
+			last_message_time: Mutex::new(now),
+			established_at: now,
+			payload_read_started: Default::default(),
+			counters: Default::default(),
+			clock: clock,
+			compression_threshold: if cfg!(feature = "compression") { compression_threshold } else { None },
+			is_compression_supported_by_peer: AtomicBool::new(false),
+			last_sent_keep_alive_nonce: AtomicUsize::new(0),
+			last_received_keep_alive_nonce: Mutex::new(0),
+		})
+	}
+
+	#[cfg(test)]
+	/// Create connection without handshake.
+	pub fn from_parts(node_id: NodeId, node_address: SocketAddr, is_inbound: bool, stream: SharedTcpStream) -> Arc<Connection> {
+		let clock: Arc<Clock> = Arc::new(SystemClock);
+		let now = clock.now();
+		Arc::new(Connection {
+			node_id: node_id,
+			node_address: node_address,
+			is_inbound: is_inbound,
+			redact_address: false,
+			stream: stream,
+			key: Random.generate().expect("generating random key pair never fails; qed"),
+			last_message_time: Mutex::new(now),
+			established_at: now,
+			payload_read_started: Default::default(),
+			counters: Default::default(),
+			clock: clock,
+			compression_threshold: None,
+			is_compression_supported_by_peer: AtomicBool::new(false),
+			last_sent_keep_alive_nonce: AtomicUsize::new(0),
+			last_received_keep_alive_nonce: Mutex::new(0),
 		})
 	}
 
@@ -951,6 +2484,16 @@ impl Connection {
 		self.is_inbound
 	}
 
+	/// Get time elapsed since connection has been established.
+	pub fn uptime(&self) -> time::Duration {
+		self.clock.now() - self.established_at
+	}
+
+	/// Get time elapsed since header of currently read message has been received.
+	pub fn pending_read_duration(&self) -> Option<time::Duration> {
+		self.payload_read_started.lock().map(|started| self.clock.now() - started)
+	}
+
 	pub fn node_id(&self) -> &NodeId {
 		&self.node_id
 	}
@@ -959,20 +2502,146 @@ impl Connection {
 		*self.last_message_time.lock()
 	}
 
+	/// Shutdown underlying stream, even if there are other references to this connection.
+	pub fn close(&self) {
+		let _ = self.stream.shutdown(Shutdown::Both);
+	}
+
 	pub fn set_last_message_time(&self, last_message_time: time::Instant) {
 		*self.last_message_time.lock() = last_message_time;
 	}
 
+	/// Get nonce for the next KeepAlive message.
+	pub fn next_keep_alive_nonce(&self) -> u64 {
+		self.last_sent_keep_alive_nonce.fetch_add(1, Ordering::Relaxed) as u64 + 1
+	}
+
+	/// Remember nonce of received KeepAlive message. Returns false if message is stale (nonce is not larger than previous).
+	/// KeepAlive messages of older nodes are not numbered (nonce is zero) => these are never considered stale.
+	pub fn on_keep_alive(&self, nonce: u64) -> bool {
+		if nonce == 0 {
+			return true;
+		}
+
+		let mut last_nonce = self.last_received_keep_alive_nonce.lock();
+		if nonce <= *last_nonce {
+			return false;
+		}
+
+		*last_nonce = nonce;
+		true
+	}
+
+	/// Does this node support compression?
+	pub fn supports_compression(&self) -> bool {
+		self.compression_threshold.is_some()
+	}
+
+	/// Remember if peer supports compression.
+	pub fn set_peer_supports_compression(&self, is_supported: bool) {
+		self.is_compression_supported_by_peer.store(is_supported, Ordering::Relaxed);
+	}
+
+	/// Are large messages compressed when sent over this connection?
+	pub fn is_compression_enabled(&self) -> bool {
+		self.compression_threshold().is_some()
+	}
+
+	fn compression_threshold(&self) -> Option<usize> {
+		match self.is_compression_supported_by_peer.load(Ordering::Relaxed) {
+			true => self.compression_threshold,
+			false => None,
+		}
+	}
+
 	pub fn node_address(&self) -> &SocketAddr {
 		&self.node_address
 	}
 
-	pub fn send_message(&self, message: Message) -> WriteMessage<SharedTcpStream> {
-		write_encrypted_message(self.stream.clone(), &self.key, message)
+	pub fn stats(&self) -> ConnectionStats {
+		ConnectionStats {
+			messages_sent: self.counters.messages_sent.load(Ordering::Relaxed),
+			messages_received: self.counters.messages_received.load(Ordering::Relaxed),
+			bytes_sent: self.counters.bytes_sent.load(Ordering::Relaxed),
+			bytes_received: self.counters.bytes_received.load(Ordering::Relaxed),
+			messages_rejected: self.counters.messages_rejected.load(Ordering::Relaxed),
+			uptime: self.uptime(),
+		}
+	}
+
+	/// Check that message is consistent with the node id, established during handshake.
+	pub fn check_message_sender(&self, message: &Message) -> Result<(), Error> {
+		match *message {
+			Message::Cluster(ClusterMessage::NodePublicKey(ref message)) if *message.node_id != self.node_id => Err(Error::NodeIdMismatch),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn on_message_rejected(&self) {
+		self.counters.messages_rejected.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Remember malformed message. Returns number of malformed messages, received in a row.
+	pub fn on_protocol_error(&self) -> usize {
+		self.counters.protocol_errors.fetch_add(1, Ordering::Relaxed) + 1
+	}
+
+	pub fn reset_protocol_errors(&self) {
+		self.counters.protocol_errors.store(0, Ordering::Relaxed);
 	}
 
-	pub fn read_message(&self) -> ReadMessage<SharedTcpStream> {
-		read_encrypted_message(self.stream.clone(), self.key.clone())
+	pub fn send_message(&self, message: Message) -> IoFuture<()> {
+		let counters = self.counters.clone();
+		let write = match self.compression_threshold() {
+			Some(compression_threshold) => write_compressed_encrypted_message(self.stream.clone(), &self.key, message, compression_threshold),
+			None => write_encrypted_message(self.stream.clone(), &self.key, message),
+		};
+		write
+			.map(move |(_, data)| {
+				counters.messages_sent.fetch_add(1, Ordering::Relaxed);
+				counters.bytes_sent.fetch_add(data.len(), Ordering::Relaxed);
+			})
+			.boxed()
+	}
+
+	/// Read header of the next message.
+	pub fn read_header(&self, max_message_size: usize) -> IoFuture<(SharedTcpStream, Result<MessageHeader, Error>)> {
+		read_header(self.stream.clone())
+			.map(move |(stream, header)| match header {
+				// do not even try to read too large messages
+				Ok(ref header) if header.size as usize > max_message_size => (stream, Err(Error::MessageTooLarge)),
+				header => (stream, header),
+			})
+			.boxed()
+	}
+
+	/// Read payload of the message with given header.
+	pub fn read_payload(&self, stream: SharedTcpStream, header: MessageHeader) -> IoFuture<(SharedTcpStream, Result<Message, Error>)> {
+		let counters = self.counters.clone();
+		let payload_read_started = self.payload_read_started.clone();
+		let message_size = MESSAGE_HEADER_SIZE + header.size as usize;
+		*payload_read_started.lock() = Some(self.clock.now());
+		read_encrypted_payload(stream, header, self.key.clone())
+			.map(move |(stream, message)| {
+				*payload_read_started.lock() = None;
+				counters.messages_received.fetch_add(1, Ordering::Relaxed);
+				counters.bytes_received.fetch_add(message_size, Ordering::Relaxed);
+				(stream, message)
+			})
+			.boxed()
+	}
+}
+
+impl fmt::Display for Connection {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}@", self.node_id)?;
+		if self.redact_address {
+			write!(f, "*:{}", self.node_address.port())?;
+		} else {
+			write!(f, "{}", self.node_address)?;
+		}
+		write!(f, " ({}, last message {}s ago)", if self.is_inbound { "inbound" } else { "outbound" },
+			(self.clock.now() - self.last_message_time()).as_secs())
 	}
 }
 
@@ -980,6 +2649,7 @@ impl ClusterView {
 	pub fn new(cluster: Arc<ClusterData>, nodes: BTreeSet<NodeId>) -> Self {
 		ClusterView {
 			core: Arc::new(Mutex::new(ClusterViewCore {
+				self_node_id: cluster.self_key_pair.public().clone(),
 				cluster: cluster,
 				nodes: nodes,
 			})),
@@ -993,26 +2663,142 @@ impl ClusterView {
 	pub fn nodes(&self) -> BTreeSet<NodeId> {
 		self.core.lock().nodes.clone()
 	}
+
+	/// Exclude all nodes, except given, from this view. Fails if some of given nodes are not a part of this view.
+	pub fn restrict_nodes(&self, nodes: &BTreeSet<NodeId>) -> Result<(), Error> {
+		let mut core = self.core.lock();
+		if !nodes.is_subset(&core.nodes) {
+			return Err(Error::InvalidNodeForRequest);
+		}
+
+		core.nodes = nodes.clone();
+		Ok(())
+	}
+
+	/// Send message to every node of given subset of this view (e.g. to nodes, which have confirmed participation
+	/// in session). Nothing is sent if subset contains node, which is not a part of this view.
+	pub fn send_to_set(&self, nodes: &BTreeSet<NodeId>, message: Message) -> Result<(), Error> {
+		let nodes = {
+			let core = self.core.lock();
+			if !nodes.is_subset(&core.nodes) {
+				return Err(Error::InvalidNodeForRequest);
+			}
+			core.cluster.ordered_nodes(nodes.iter())
+		};
+
+		let mut disconnected_nodes = BTreeSet::new();
+		for node in nodes {
+			if self.send(&node, message.clone()).is_err() {
+				disconnected_nodes.insert(node);
+			}
+		}
+
+		if disconnected_nodes.is_empty() {
+			Ok(())
+		} else {
+			Err(Error::NodesDisconnected(disconnected_nodes))
+		}
+	}
+
+	fn broadcast_to_others(&self, exclude: Option<&NodeId>, message: Message) -> Result<(), Error> {
+		let disconnected_nodes: BTreeSet<_> = self.send_to_others(exclude, message).into_iter()
+			.filter(|&(_, ref result)| result.is_err())
+			.map(|(node, _)| node)
+			.collect();
+		if disconnected_nodes.is_empty() {
+			Ok(())
+		} else {
+			Err(Error::NodesDisconnected(disconnected_nodes))
+		}
+	}
+
+	fn send_to_others(&self, exclude: Option<&NodeId>, message: Message) -> BTreeMap<NodeId, Result<(), Error>> {
+		let core = self.core.lock();
+		let mut results = BTreeMap::new();
+		let nodes = core.cluster.ordered_nodes(core.nodes.iter().filter(|n| *n != &core.self_node_id && Some(*n) != exclude));
+		for node in &nodes {
+			trace!(target: "secretstore_net", "{}: sent message {} to {}", core.cluster.self_key_pair.public(), message, node);
+			// try to send message to all other nodes, but remember every disconnected one
+			let result = match core.cluster.connection(node) {
+				Some(connection) => {
+					ClusterCore::send_message(core.cluster.clone(), connection, message.clone());
+					Ok(())
+				},
+				None => Err(Error::NodeDisconnected),
+			};
+			results.insert(node.clone(), result);
+		}
+		results
+	}
 }
 
 impl Cluster for ClusterView {
 	fn broadcast(&self, message: Message) -> Result<(), Error> {
+		self.broadcast_to_others(None, message)
+	}
+
+	fn broadcast_except(&self, exclude: &NodeId, message: Message) -> Result<(), Error> {
+		self.broadcast_to_others(Some(exclude), message)
+	}
+
+	fn broadcast_detailed(&self, message: Message) -> BTreeMap<NodeId, Result<(), Error>> {
+		self.send_to_others(None, message)
+	}
+
+	fn send(&self, to: &NodeId, message: Message) -> Result<(), Error> {
 		let core = self.core.lock();
-		for node in core.nodes.iter().filter(|n| *n != core.cluster.self_key_pair.public()) {
-			trace!(target: "secretstore_net", "{}: sent message {} to {}", core.cluster.self_key_pair.public(), message, node);
-			let connection = core.cluster.connection(node).ok_or(Error::NodeDisconnected)?;
-			core.cluster.spawn(connection.send_message(message.clone()))
+		if !core.nodes.contains(to) {
+			warn!(target: "secretstore_net", "{}: refused to send message {} to non-session node {}", core.self_node_id, message, to);
+			return Err(Error::InvalidNodeForRequest);
+		}
+
+		trace!(target: "secretstore_net", "{}: sent message {} to {}", core.self_node_id, message, to);
+		if to == &core.self_node_id {
+			// there's no connection to self => deliver message in-process
+			// (asynchronously, because sender could hold session lock at the moment)
+			let data = core.cluster.clone();
+			core.cluster.spawn(lazy(move || {
+				ClusterCore::process_self_message(data, message);
+				finished::<(), ()>(())
+			}));
+			return Ok(());
 		}
+
+		let connection = core.cluster.connection(to).ok_or(Error::NodeDisconnected)?;
+		ClusterCore::send_message(core.cluster.clone(), connection, message);
 		Ok(())
 	}
 
-	fn send(&self, to: &NodeId, message: Message) -> Result<(), Error> {
+	fn blacklist(&self, node: &NodeId) {
 		let core = self.core.lock();
-		trace!(target: "secretstore_net", "{}: sent message {} to {}", core.cluster.self_key_pair.public(), message, to);
-		let connection = core.cluster.connection(to).ok_or(Error::NodeDisconnected)?;
-		core.cluster.spawn(connection.send_message(message));
+		warn!(target: "secretstore_net", "{}: blacklisting node {}", core.cluster.self_key_pair.public(), node);
+		if let Some(connection) = core.cluster.connection(node) {
+			ClusterCore::disconnect(&core.cluster, connection);
+		}
+		core.cluster.connections.blacklist(node, core.cluster.config.blacklist_interval);
+		core.cluster.sessions.remove_queued_messages(node);
+	}
+}
+
+impl Cluster for NullCluster {
+	fn broadcast(&self, _message: Message) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn broadcast_except(&self, _exclude: &NodeId, _message: Message) -> Result<(), Error> {
+		Ok(())
+	}
+
+	fn broadcast_detailed(&self, _message: Message) -> BTreeMap<NodeId, Result<(), Error>> {
+		BTreeMap::new()
+	}
+
+	fn send(&self, _to: &NodeId, _message: Message) -> Result<(), Error> {
 		Ok(())
 	}
+
+	fn blacklist(&self, _node: &NodeId) {
+	}
 }
 
 impl ClusterClientImpl {
@@ -1021,24 +2807,157 @@ impl ClusterClientImpl {
 			data: data,
 		}
 	}
-}
-
-impl ClusterClient for ClusterClientImpl {
-	fn cluster_state(&self) -> ClusterState {
-		self.data.connections.cluster_state()
-	}
 
-	fn new_encryption_session(&self, session_id: SessionId, threshold: usize) -> Result<Arc<EncryptionSession>, Error> {
+	/// Create && initialize new encryption session.
+	fn start_encryption_session(&self, session_id: SessionId, threshold: usize) -> Result<Arc<EncryptionSessionImpl>, Error> {
 		let mut connected_nodes = self.data.connections.connected_nodes();
 		connected_nodes.insert(self.data.self_key_pair.public().clone());
 
-		let cluster = Arc::new(ClusterView::new(self.data.clone(), connected_nodes.clone()));
-		let session = self.data.sessions.new_encryption_session(self.data.self_key_pair.public().clone(), session_id.clone(), cluster)?;
-		session.initialize(threshold, connected_nodes)?;
-		Ok(EncryptionSessionWrapper::new(Arc::downgrade(&self.data), session_id, session))
+		// communicating to all other nodes is crucial for encryption session
+		let required_nodes = self.data.sessions.nodes.read().clone();
+		self.start_encryption_session_with_nodes(session_id, threshold, connected_nodes, &required_nodes)
 	}
 
-	fn new_decryption_session(&self, session_id: SessionId, requestor_signature: Signature, is_shadow_decryption: bool) -> Result<Arc<DecryptionSession>, Error> {
+	/// Create && initialize new encryption session on given nodes. Session fails to start if any of required nodes is missing.
+	fn start_encryption_session_with_nodes(&self, session_id: SessionId, threshold: usize, nodes: BTreeSet<NodeId>, required_nodes: &BTreeSet<NodeId>) -> Result<Arc<EncryptionSessionImpl>, Error> {
+		self.data.check_accepting_sessions()?;
+
+		// check threshold before session is created, so that it won't be left in the cluster
+		check_threshold(threshold, &nodes)?;
+
+		let cluster = Arc::new(ClusterView::new(self.data.clone(), nodes.clone()));
+		let session = self.data.sessions.new_encryption_session_with_nodes(self.data.self_key_pair.public().clone(), session_id.clone(), cluster, required_nodes)?;
+		self.data.on_session_started(&session_id);
+		if let Err(err) = session.initialize(threshold, nodes) {
+			self.data.on_session_failed(&session_id, err.clone());
+			return Err(err);
+		}
+		if session.state() == EncryptionSessionState::Finished {
+			self.data.on_session_completed(&session_id);
+		}
+		Ok(session)
+	}
+}
+
+impl ClusterClient for ClusterClientImpl {
+	fn cluster_state(&self) -> ClusterState {
+		self.data.connections.cluster_state()
+	}
+
+	fn self_node_id(&self) -> NodeId {
+		self.data.self_key_pair.public().clone()
+	}
+
+	fn configured_nodes(&self) -> BTreeMap<NodeId, (String, u16)> {
+		self.data.connections.nodes.read().clone()
+	}
+
+	fn wait_for_quorum(&self, min_nodes: usize, timeout: time::Duration) -> BoxFuture<(), Error> {
+		ClusterData::wait_for_quorum(&self.data, min_nodes, timeout)
+	}
+
+	fn run_keep_alive_now(&self) {
+		ClusterCore::keep_alive(self.data.clone());
+	}
+
+	fn touch_connection(&self, node: &NodeId) -> Result<(), Error> {
+		let connection = self.data.connection(node).ok_or(Error::NodeDisconnected)?;
+		connection.set_last_message_time(self.data.clock.now());
+		Ok(())
+	}
+
+	fn ping(&self, node: &NodeId) -> BoxFuture<time::Duration, Error> {
+		ClusterData::ping(&self.data, node)
+	}
+
+	fn health(&self) -> ClusterHealth {
+		let connected_nodes = self.data.connections.connected_nodes().len();
+		let configured_nodes = self.data.connections.nodes.read().len();
+		ClusterHealth {
+			has_quorum: connected_nodes * 100 >= configured_nodes * self.data.config.ready_quorum_percent,
+			connected_nodes: connected_nodes,
+			configured_nodes: configured_nodes,
+			active_sessions: self.data.sessions.active_sessions_count(),
+			oldest_session_age: self.data.sessions.oldest_session_creation_time().map(|t| self.data.clock.now() - t),
+			shortest_connection_uptime: self.data.connections.active_connections().iter().map(|c| c.uptime()).min(),
+		}
+	}
+
+	fn set_event_sink(&self, event_sink: Arc<EventSink>) {
+		*self.data.event_sink.write() = Some(event_sink);
+	}
+
+	fn set_accepting_sessions(&self, is_accepting: bool) {
+		self.data.accepting_sessions.store(is_accepting, Ordering::Relaxed);
+	}
+
+	fn is_connected(&self, node: &NodeId) -> bool {
+		self.data.connections.is_connected(node)
+	}
+
+	fn sessions_snapshot(&self) -> SessionsSnapshot {
+		SessionsSnapshot {
+			encryption_sessions: self.data.sessions.active_encryption_sessions(),
+			decryption_sessions: self.data.sessions.active_decryption_sessions(),
+		}
+	}
+
+	fn session_report(&self) -> Vec<SessionReport> {
+		self.data.sessions.session_report()
+	}
+
+	fn session_info(&self, session_id: &SessionId) -> Option<SessionInfo> {
+		self.data.sessions.encryption_session_info(session_id)
+	}
+
+	fn connection_stats(&self) -> BTreeMap<NodeId, ConnectionStats> {
+		self.data.connections.connection_stats()
+	}
+
+	fn connection_metrics(&self) -> ConnectionMetrics {
+		self.data.connections.connection_metrics()
+	}
+
+	fn recent_connection_events(&self, limit: usize) -> Vec<ConnectionEvent> {
+		self.data.connections.recent_events(limit)
+	}
+
+	fn new_encryption_session(&self, session_id: SessionId, threshold: usize) -> Result<Arc<EncryptionSession>, Error> {
+		let session = self.start_encryption_session(session_id.clone(), threshold)?;
+		Ok(EncryptionSessionWrapper::new(Arc::downgrade(&self.data), session_id, session))
+	}
+
+	fn new_encryption_session_with_nodes(&self, session_id: SessionId, threshold: usize, nodes: BTreeSet<NodeId>) -> Result<Arc<EncryptionSession>, Error> {
+		if !nodes.contains(self.data.self_key_pair.public()) {
+			return Err(Error::InvalidNodesConfiguration);
+		}
+		if nodes.iter().any(|n| n != self.data.self_key_pair.public() && !self.data.connections.is_connected(n)) {
+			return Err(Error::NodeDisconnected);
+		}
+
+		let session = self.start_encryption_session_with_nodes(session_id.clone(), threshold, nodes.clone(), &nodes)?;
+		Ok(EncryptionSessionWrapper::new(Arc::downgrade(&self.data), session_id, session))
+	}
+
+	fn new_encryption_session_future(&self, session_id: SessionId, threshold: usize) -> BoxFuture<Public, Error> {
+		let session = match self.start_encryption_session(session_id.clone(), threshold) {
+			Ok(session) => session,
+			Err(err) => return failed(err).boxed(),
+		};
+
+		// session is removed from the cluster when wrapper is dropped => keep it until completion
+		let wrapper = EncryptionSessionWrapper::new(Arc::downgrade(&self.data), session_id, session.clone());
+		session.completion()
+			.then(move |result| {
+				let _wrapper = wrapper;
+				result.unwrap_or(Err(Error::Io("encryption session has been dropped".into())))
+			})
+			.boxed()
+	}
+
+	fn new_decryption_session(&self, session_id: SessionId, requestor_signature: Signature, is_shadow_decryption: bool) -> Result<Arc<DecryptionSession>, Error> {
+		self.data.check_accepting_sessions()?;
+
 		let mut connected_nodes = self.data.connections.connected_nodes();
 		connected_nodes.insert(self.data.self_key_pair.public().clone());
 
@@ -1049,11 +2968,40 @@ impl ClusterClient for ClusterClientImpl {
 		Ok(DecryptionSessionWrapper::new(Arc::downgrade(&self.data), session_id, access_key, session))
 	}
 
+	fn add_node(&self, node: NodeId, address: (String, u16)) -> Result<(), Error> {
+		self.data.connections.add_node(node.clone(), address.clone())?;
+		self.data.sessions.nodes.write().insert(node.clone());
+		// address of already connected node could be updated => do not establish duplicate connection
+		if !self.data.connections.is_connected(&node) {
+			ClusterCore::connect_node(self.data.clone(), &node, address);
+		}
+		Ok(())
+	}
+
+	fn remove_node(&self, node: &NodeId) {
+		if let Some(connection) = self.data.connection(node) {
+			ClusterCore::disconnect(&self.data, connection);
+		}
+		self.data.sessions.nodes.write().remove(node);
+		self.data.connections.remove_node(node);
+		self.data.sessions.on_connection_timeout(node);
+	}
+
 	#[cfg(test)]
 	fn connect(&self) {
+		// we're explicitly asked to connect => ignore reconnect intervals
+		self.data.connections.backoff.write().clear();
 		ClusterCore::connect_disconnected_nodes(self.data.clone());
 	}
 
+	fn abort_session(&self, session_id: &SessionId) -> Result<(), Error> {
+		self.data.sessions.abort_encryption_session(session_id)
+	}
+
+	fn abort_decryption_session(&self, session_id: &DecryptionSessionId) -> Result<(), Error> {
+		self.data.sessions.abort_decryption_session(session_id)
+	}
+
 	#[cfg(test)]
 	fn make_faulty_encryption_sessions(&self) {
 		self.data.sessions.make_faulty_encryption_sessions();
@@ -1123,22 +3071,155 @@ impl Drop for DecryptionSessionWrapper {
 	}
 }
 
+/// When there are two connections between the same pair of nodes, check if given connection must be kept.
+/// The agreement is that node with lower id must establish connection to node with higher id.
+fn should_keep_connection(self_id: &NodeId, peer_id: &NodeId, is_inbound: bool) -> bool {
+	!((self_id < peer_id && is_inbound) || (self_id > peer_id && !is_inbound))
+}
+
+/// Create thread pool for the cluster.
+fn make_pool(config: &ClusterConfiguration) -> CpuPool {
+	CpuPoolBuilder::new()
+		.pool_size(config.threads)
+		.name_prefix(config.threads_name_prefix.clone())
+		.create()
+}
+
+/// Default threads name prefix: 'ss-' + first 4 bytes of node id.
+fn default_threads_name_prefix(node: &NodeId) -> String {
+	let short_node_id: String = node[0..4].iter().map(|b| format!("{:02x}", b)).collect();
+	format!("ss-{}-", short_node_id)
+}
+
+/// Bind listener to given address. Address is reused, so that node could be restarted while
+/// connections from previous run are in TIME_WAIT state.
+fn make_listener(listen_address: &SocketAddr, backlog: i32) -> io::Result<::std::net::TcpListener> {
+	let builder = match *listen_address {
+		SocketAddr::V4(_) => TcpBuilder::new_v4()?,
+		SocketAddr::V6(_) => TcpBuilder::new_v6()?,
+	};
+	builder.reuse_address(true)?;
+	builder.bind(listen_address)?;
+	builder.listen(backlog)
+}
+
+fn panic_message(panic: &Box<Any + Send>) -> String {
+	panic.downcast_ref::<&str>().map(|m| m.to_string())
+		.or_else(|| panic.downcast_ref::<String>().cloned())
+		.unwrap_or_else(|| "unknown panic".into())
+}
+
+fn write_retry_delay(interval: time::Duration, attempt: usize) -> time::Duration {
+	let max_delay = time::Duration::from_millis(MAX_WRITE_RETRY_INTERVAL);
+	1u32.checked_shl(attempt as u32)
+		.and_then(|multiplier| interval.checked_mul(multiplier))
+		.map(|delay| ::std::cmp::min(delay, max_delay))
+		.unwrap_or(max_delay)
+}
+
+fn random_jitter(interval: time::Duration) -> time::Duration {
+	random_delay(interval / 4)
+}
+
+fn random_delay(max_delay: time::Duration) -> time::Duration {
+	let max_delay_ms = max_delay.as_secs() * 1000 + (max_delay.subsec_nanos() / 1_000_000) as u64;
+	time::Duration::from_millis(rand::thread_rng().gen_range(0, max_delay_ms + 1))
+}
+
 fn make_socket_address(address: &str, port: u16) -> Result<SocketAddr, Error> {
-	let ip_address: IpAddr = address.parse().map_err(|_| Error::InvalidNodeAddress)?;
-	Ok(SocketAddr::new(ip_address, port))
+	// IPv6 literal could be written both with and without brackets
+	let address = if address.starts_with('[') && address.ends_with(']') {
+		&address[1..address.len() - 1]
+	} else {
+		address
+	};
+
+	// literal ip address doesn't require resolution
+	if let Ok(ip_address) = address.parse::<IpAddr>() {
+		return Ok(SocketAddr::new(ip_address, port));
+	}
+
+	// else resolve host name, preferring IPv4 addresses
+	let addresses: Vec<_> = (address, port).to_socket_addrs().map_err(|_| Error::InvalidNodeAddress)?.collect();
+	addresses.iter()
+		.find(|address| address.is_ipv4())
+		.or_else(|| addresses.first())
+		.cloned()
+		.ok_or(Error::InvalidNodeAddress)
+}
+
+/// Check that this node is not going to connect to itself, thinking that it is some other node.
+/// Returns listen address of this node.
+fn check_nodes_addresses(config: &ClusterConfiguration) -> Result<SocketAddr, Error> {
+	for (node_id, &(ref node_host, node_port)) in &config.advertised_addresses {
+		if !config.nodes.contains_key(node_id) {
+			return Err(Error::InvalidConfiguration(format!("advertised address {}:{} is specified for unknown node {}", node_host, node_port, node_id)));
+		}
+		if node_host.is_empty() || node_port == 0 {
+			return Err(Error::InvalidConfiguration(format!("invalid advertised address {}:{} of node {}", node_host, node_port, node_id)));
+		}
+	}
+
+	let listen_address = make_socket_address(&config.listen_address.0, config.listen_address.1)?;
+	for (node_id, node_address) in &config.nodes {
+		// other nodes are connected using advertised address (if any)
+		if node_id != config.self_key_pair.public() {
+			let &(ref node_host, node_port) = config.advertised_addresses.get(node_id).unwrap_or(node_address);
+			check_node_address(&listen_address, node_id, node_host, node_port)?;
+			continue;
+		}
+
+		// host name could be unresolvable at the moment => only check what we can
+		match make_socket_address(&node_address.0, node_address.1) {
+			Ok(ref node_address) if !is_same_address(&listen_address, node_address) =>
+				return Err(Error::InvalidConfiguration(format!("this node is listed at {}, but listens at {}", node_address, listen_address))),
+			_ => (),
+		}
+	}
+
+	Ok(listen_address)
+}
+
+/// Check that address of other node is well-formed && differs from the listen address of this node.
+fn check_node_address(listen_address: &SocketAddr, node_id: &NodeId, node_host: &str, node_port: u16) -> Result<(), Error> {
+	if node_host.is_empty() || node_port == 0 {
+		return Err(Error::InvalidConfiguration(format!("invalid address {}:{} of node {}", node_host, node_port, node_id)));
+	}
+
+	// host names of other nodes could be unresolvable at the moment => only check what we can
+	match make_socket_address(node_host, node_port) {
+		Ok(ref node_address) if is_same_address(listen_address, node_address) =>
+			Err(Error::InvalidConfiguration(format!("node {} is listed at {}, which is the listen address of this node", node_id, node_address))),
+		_ => Ok(()),
+	}
+}
+
+fn is_same_address(listen_address: &SocketAddr, node_address: &SocketAddr) -> bool {
+	listen_address.port() == node_address.port()
+		&& (listen_address.ip().is_unspecified() || listen_address.ip() == node_address.ip())
 }
 
 #[cfg(test)]
 pub mod tests {
 	use std::sync::Arc;
+	use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 	use std::time;
-	use std::collections::VecDeque;
+	use std::collections::{BTreeMap, BTreeSet, VecDeque};
+	use std::net::SocketAddr;
+	use futures::{finished, failed, lazy, Future};
+	use futures_cpupool::CpuPool;
 	use parking_lot::Mutex;
 	use tokio_core::reactor::Core;
-	use ethkey::{Random, Generator};
-	use key_server_cluster::{NodeId, SessionId, Error, DummyAclStorage, DummyKeyStorage};
-	use key_server_cluster::message::Message;
-	use key_server_cluster::cluster::{Cluster, ClusterCore, ClusterConfiguration};
+	use tokio_core::net::TcpStream;
+	use tokio_io::AsyncWrite;
+	use tokio_io::io::write_all;
+	use ethkey::{Random, Generator, Public};
+	use key_server_cluster::{NodeId, SessionId, Error, DummyAclStorage, DummyKeyStorage, KeyStorage};
+	use key_server_cluster::message::{self, Message, ClusterMessage, EncryptionMessage};
+	#[cfg(feature = "compression")]
+	use key_server_cluster::io::{encrypt_message, serialize_message};
+	use key_server_cluster::cluster::{Clock, Cluster, ClusterCore, ClusterConfiguration, ClusterConnections, ClusterData, ClusterView, Connection, ConnectionStats, EventSink,
+		ConnectionEventKind, SessionKind, MAX_ACTIVE_SESSIONS, ENCRYPTION_SESSION_TIMEOUT_INTERVAL, make_listener, make_pool, make_socket_address, should_keep_connection, write_retry_delay};
 	use key_server_cluster::encryption_session::{Session as EncryptionSession, SessionState as EncryptionSessionState};
 
 	#[derive(Debug)]
@@ -1150,7 +3231,14 @@ pub mod tests {
 	#[derive(Debug, Default)]
 	struct DummyClusterData {
 		nodes: Vec<NodeId>,
-		messages: VecDeque<(NodeId, Message)>,
+		/// Sequence number of the next sent message.
+		next_message_id: usize,
+		/// Broadcasted messages, along with excluded node.
+		broadcasts: VecDeque<(usize, Option<NodeId>, Message)>,
+		/// Messages, sent to the single node.
+		direct: VecDeque<(usize, NodeId, Message)>,
+		/// Copies of broadcasted message, which are not yet taken by take_message.
+		pending: VecDeque<(NodeId, Message)>,
 	}
 
 	impl DummyCluster {
@@ -1169,26 +3257,175 @@ pub mod tests {
 			self.data.lock().nodes.push(node);
 		}
 
+		/// Take next message (both broadcasted and direct) in the order these were sent.
+		/// Broadcasted message is returned once for every other node.
 		pub fn take_message(&self) -> Option<(NodeId, Message)> {
-			self.data.lock().messages.pop_front()
+			let mut data = self.data.lock();
+			loop {
+				if let Some(message) = data.pending.pop_front() {
+					return Some(message);
+				}
+
+				let is_broadcast_next = match (data.broadcasts.front(), data.direct.front()) {
+					(Some(&(broadcast_id, _, _)), Some(&(direct_id, _, _))) => broadcast_id < direct_id,
+					(Some(_), None) => true,
+					(None, Some(_)) => false,
+					(None, None) => return None,
+				};
+				if !is_broadcast_next {
+					return data.direct.pop_front().map(|(_, to, message)| (to, message));
+				}
+
+				let (_, exclude, message) = data.broadcasts.pop_front().expect("checked above; qed");
+				let all_nodes: Vec<_> = data.nodes.iter().cloned().filter(|n| n != &self.id && Some(n) != exclude.as_ref()).collect();
+				data.pending.extend(all_nodes.into_iter().map(|node| (node, message.clone())));
+			}
+		}
+
+		/// Take all not yet taken messages in the order these were sent.
+		pub fn drain_messages(&self) -> Vec<(NodeId, Message)> {
+			let mut messages = Vec::new();
+			while let Some(message) = self.take_message() {
+				messages.push(message);
+			}
+			messages
+		}
+
+		/// Number of messages, which are not yet taken. Broadcasted message is counted once for every other node.
+		pub fn message_count(&self) -> usize {
+			let data = self.data.lock();
+			let broadcasts_count: usize = data.broadcasts.iter()
+				.map(|&(_, ref exclude, _)| data.nodes.iter().filter(|n| *n != &self.id && Some(*n) != exclude.as_ref()).count())
+				.sum();
+			data.pending.len() + data.direct.len() + broadcasts_count
+		}
+
+		/// Take next broadcasted message.
+		pub fn take_broadcast(&self) -> Option<Message> {
+			self.data.lock().broadcasts.pop_front().map(|(_, _, message)| message)
+		}
+
+		/// Take next message, sent to the single node.
+		pub fn take_direct(&self) -> Option<(NodeId, Message)> {
+			self.data.lock().direct.pop_front().map(|(_, to, message)| (to, message))
+		}
+
+		/// Get all not yet taken messages (both broadcasted and direct), which are sent to given node.
+		pub fn sent_to(&self, node: &NodeId) -> Vec<Message> {
+			let data = self.data.lock();
+			let mut messages: Vec<_> = data.broadcasts.iter()
+				.filter(|&&(_, ref exclude, _)| exclude.as_ref() != Some(node))
+				.map(|&(id, _, ref message)| (id, message.clone()))
+				.chain(data.direct.iter()
+					.filter(|&&(_, ref to, _)| to == node)
+					.map(|&(id, _, ref message)| (id, message.clone())))
+				.collect();
+			messages.sort_by_key(|&(id, _)| id);
+
+			data.pending.iter()
+				.filter(|&&(ref to, _)| to == node)
+				.map(|&(_, ref message)| message.clone())
+				.chain(messages.into_iter().map(|(_, message)| message))
+				.collect()
 		}
 	}
 
 	impl Cluster for DummyCluster {
 		fn broadcast(&self, message: Message) -> Result<(), Error> {
 			let mut data = self.data.lock();
-			let all_nodes: Vec<_> = data.nodes.iter().cloned().filter(|n| n != &self.id).collect();
-			for node in all_nodes {
-				data.messages.push_back((node, message.clone()));
-			}
+			let message_id = data.next_message_id;
+			data.next_message_id += 1;
+			data.broadcasts.push_back((message_id, None, message));
+			Ok(())
+		}
+
+		fn broadcast_except(&self, exclude: &NodeId, message: Message) -> Result<(), Error> {
+			let mut data = self.data.lock();
+			let message_id = data.next_message_id;
+			data.next_message_id += 1;
+			data.broadcasts.push_back((message_id, Some(exclude.clone()), message));
 			Ok(())
 		}
 
+		fn broadcast_detailed(&self, message: Message) -> BTreeMap<NodeId, Result<(), Error>> {
+			let mut data = self.data.lock();
+			let message_id = data.next_message_id;
+			data.next_message_id += 1;
+			data.broadcasts.push_back((message_id, None, message));
+			data.nodes.iter()
+				.filter(|n| *n != &self.id)
+				.map(|n| (n.clone(), Ok(())))
+				.collect()
+		}
+
 		fn send(&self, to: &NodeId, message: Message) -> Result<(), Error> {
 			debug_assert!(&self.id != to);
-			self.data.lock().messages.push_back((to.clone(), message));
+			let mut data = self.data.lock();
+			let message_id = data.next_message_id;
+			data.next_message_id += 1;
+			data.direct.push_back((message_id, to.clone(), message));
 			Ok(())
 		}
+
+		fn blacklist(&self, _node: &NodeId) {
+		}
+	}
+
+	#[derive(Default)]
+	pub struct RecordingEventSink {
+		pub events: Mutex<Vec<String>>,
+	}
+
+	impl EventSink for RecordingEventSink {
+		fn on_session_started(&self, session_id: SessionId) {
+			self.events.lock().push(format!("started {:?}", session_id));
+		}
+
+		fn on_session_completed(&self, session_id: SessionId) {
+			self.events.lock().push(format!("completed {:?}", session_id));
+		}
+
+		fn on_session_failed(&self, session_id: SessionId, _error: Error) {
+			self.events.lock().push(format!("failed {:?}", session_id));
+		}
+	}
+
+	pub struct FakeClock {
+		now: Mutex<time::Instant>,
+	}
+
+	impl FakeClock {
+		pub fn new() -> Self {
+			FakeClock {
+				now: Mutex::new(time::Instant::now()),
+			}
+		}
+
+		pub fn advance(&self, duration: time::Duration) {
+			*self.now.lock() += duration;
+		}
+	}
+
+	impl Clock for FakeClock {
+		fn now(&self) -> time::Instant {
+			*self.now.lock()
+		}
+	}
+
+	/// Drive clusters by explicit core turns, advancing fake clock after every turn, until all connections are
+	/// established. Turn returns as soon as some event is processed => result depends on the number of turns,
+	/// not on the wall-clock time. Returns false if connections are not established after given number of turns.
+	pub fn advance_and_settle(core: &mut Core, clock: &FakeClock, clusters: &[Arc<ClusterCore>], steps: usize) -> bool {
+		for _ in 0..steps {
+			if clusters.iter().all(all_connections_established) {
+				return true;
+			}
+
+			// timeout only guards against waiting forever when there are no more events
+			core.turn(Some(time::Duration::from_millis(100)));
+			clock.advance(time::Duration::from_millis(1));
+		}
+		clusters.iter().all(all_connections_established)
 	}
 
 	pub fn loop_until<F>(core: &mut Core, timeout: time::Duration, predicate: F) where F: Fn() -> bool {
@@ -1205,30 +3442,75 @@ pub mod tests {
 		}
 	}
 
+	pub fn loop_for(core: &mut Core, timeout: time::Duration) {
+		let start = time::Instant::now();
+		while time::Instant::now() - start < timeout {
+			core.turn(Some(time::Duration::from_millis(1)));
+		}
+	}
+
+	/// Turn the core until the cluster produces message, which satisfies matcher. All messages, that are
+	/// taken before the matching one, are dropped. Panics (dumping all taken messages) on timeout.
+	pub fn wait_for_message<F>(core: &mut Core, cluster: &DummyCluster, timeout: time::Duration, matcher: F) -> (NodeId, Message) where F: Fn(&Message) -> bool {
+		let start = time::Instant::now();
+		let mut seen_messages = Vec::new();
+		loop {
+			while let Some((to, message)) = cluster.take_message() {
+				if matcher(&message) {
+					return (to, message);
+				}
+				seen_messages.push((to, message));
+			}
+
+			if time::Instant::now() - start > timeout {
+				panic!("no matching message in {:?}. Seen messages: {:?}", timeout, seen_messages);
+			}
+
+			core.turn(Some(time::Duration::from_millis(1)));
+		}
+	}
+
 	pub fn all_connections_established(cluster: &Arc<ClusterCore>) -> bool {
 		cluster.config().nodes.keys()
 			.filter(|p| *p != cluster.config().self_key_pair.public())
-			.all(|p| cluster.connection(p).is_some())
+			.all(|p| cluster.data.connections.is_connected(p))
 	}
 
 	pub fn make_clusters(core: &Core, ports_begin: u16, num_nodes: usize) -> Vec<Arc<ClusterCore>> {
+		make_clusters_with_config(core, ports_begin, num_nodes, |_, _| ())
+	}
+
+	pub fn make_clusters_with_config<F>(core: &Core, ports_begin: u16, num_nodes: usize, configure: F) -> Vec<Arc<ClusterCore>> where F: Fn(usize, &mut ClusterConfiguration) {
+		make_clusters_configs(ports_begin, num_nodes, configure).into_iter()
+			.map(|params| ClusterCore::new(core.handle(), params).unwrap())
+			.collect()
+	}
+
+	pub fn make_clusters_configs<F>(ports_begin: u16, num_nodes: usize, configure: F) -> Vec<ClusterConfiguration> where F: Fn(usize, &mut ClusterConfiguration) {
 		let key_pairs: Vec<_> = (0..num_nodes).map(|_| Random.generate().unwrap()).collect();
-		let cluster_params: Vec<_> = (0..num_nodes).map(|i| ClusterConfiguration {
-			threads: 1,
-			self_key_pair: key_pairs[i].clone(),
-			listen_address: ("127.0.0.1".to_owned(), ports_begin + i as u16),
-			nodes: key_pairs.iter().enumerate()
+		let cluster_params: Vec<_> = (0..num_nodes).map(|i| ClusterConfiguration::new(
+			1,
+			key_pairs[i].clone(),
+			("127.0.0.1".to_owned(), ports_begin + i as u16),
+			key_pairs.iter().enumerate()
 				.map(|(j, kp)| (kp.public().clone(), ("127.0.0.1".into(), ports_begin + j as u16)))
 				.collect(),
-			allow_connecting_to_higher_nodes: false,
-			key_storage: Arc::new(DummyKeyStorage::default()),
-			acl_storage: Arc::new(DummyAclStorage::default()),
-		}).collect();
-		let clusters: Vec<_> = cluster_params.into_iter().enumerate()
-			.map(|(_, params)| ClusterCore::new(core.handle(), params).unwrap())
-			.collect();
+			false,
+			Arc::new(DummyKeyStorage::default()),
+			Arc::new(DummyAclStorage::default()),
+		)).collect();
+		cluster_params.into_iter().enumerate()
+			.map(|(i, mut params)| { configure(i, &mut params); params })
+			.collect()
+	}
 
-		clusters
+	pub fn make_clusters_with_addresses<F>(core: &Core, ports_begin: u16, num_nodes: usize, address: F) -> Vec<Arc<ClusterCore>> where F: Fn(usize) -> &'static str {
+		make_clusters_with_config(core, ports_begin, num_nodes, |i, config| {
+			config.listen_address.0 = address(i).into();
+			for node_address in config.nodes.values_mut() {
+				node_address.0 = address((node_address.1 - ports_begin) as usize).into();
+			}
+		})
 	}
 
 	pub fn run_clusters(clusters: &[Arc<ClusterCore>]) {
@@ -1243,11 +3525,59 @@ pub mod tests {
 	#[test]
 	fn cluster_connects_to_other_nodes() {
 		let mut core = Core::new().unwrap();
-		let clusters = make_clusters(&core, 6010, 3);
+		let clock = Arc::new(FakeClock::new());
+		let clusters = make_clusters_with_config(&core, 6010, 3, |_, config| {
+			config.clock = clock.clone() as Arc<Clock>;
+			// initial connect delay is a real-time timer => connect to all nodes immediately
+			config.initial_connect_delay = time::Duration::from_millis(0);
+		});
+		run_clusters(&clusters);
+		assert!(advance_and_settle(&mut core, &clock, &clusters, 1000));
+	}
+
+	#[test]
+	fn cluster_connects_to_other_nodes_over_ipv6() {
+		// IPv6 could be disabled on test machine
+		if ::std::net::TcpListener::bind("[::1]:0").is_err() {
+			return;
+		}
+
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_addresses(&core, 6143, 3, |_| "::1");
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+	}
+
+	#[test]
+	fn cluster_connects_to_other_nodes_over_mixed_ipv4_and_ipv6() {
+		// IPv6 could be disabled on test machine
+		if ::std::net::TcpListener::bind("[::1]:0").is_err() {
+			return;
+		}
+
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_addresses(&core, 6146, 3, |i| if i % 2 == 0 { "[::1]" } else { "127.0.0.1" });
 		run_clusters(&clusters);
 		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
 	}
 
+	#[test]
+	fn socket_address_is_made_from_ipv6_literal() {
+		let address: SocketAddr = "[::1]:8083".parse().unwrap();
+		assert_eq!(make_socket_address("::1", 8083).unwrap(), address);
+		assert_eq!(make_socket_address("[::1]", 8083).unwrap(), address);
+	}
+
+	#[test]
+	fn connection_from_lower_node_to_higher_node_is_kept() {
+		let lower: NodeId = [1u8; 64].into();
+		let higher: NodeId = [2u8; 64].into();
+		assert!(should_keep_connection(&lower, &higher, false));
+		assert!(!should_keep_connection(&lower, &higher, true));
+		assert!(should_keep_connection(&higher, &lower, true));
+		assert!(!should_keep_connection(&higher, &lower, false));
+	}
+
 	#[test]
 	fn cluster_wont_start_encryption_session_if_not_fully_connected() {
 		let core = Core::new().unwrap();
@@ -1308,4 +3638,1633 @@ pub mod tests {
 			}
 		}
 	}
+
+	#[test]
+	fn blacklisted_node_is_disconnected_and_not_reconnected() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6100, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// blacklist node1 on node0
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		cluster_view.blacklist(&node1);
+		assert!(clusters[0].connection(&node1).is_none());
+		assert!(clusters[0].data.connections.is_blacklisted(&node1));
+
+		// neither outbound nor inbound connections are established with blacklisted node
+		clusters[0].client().connect();
+		clusters[1].client().connect();
+		loop_for(&mut core, time::Duration::from_millis(100));
+		assert!(clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn silent_connection_is_removed_after_disconnect_timeout() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6103, 2, |_, config| {
+			config.disconnect_timeout = time::Duration::from_secs(1);
+			config.maintain_interval = time::Duration::from_millis(100);
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// run maintain procedures on the 'higher' node only, which never initiates connections
+		let (lower, higher) = if clusters[0].config().self_key_pair.public() < clusters[1].config().self_key_pair.public() { (0, 1) } else { (1, 0) };
+		let lower_node = clusters[lower].config().self_key_pair.public().clone();
+		ClusterCore::schedule_maintain(&core.handle(), clusters[higher].data.clone());
+		loop_until(&mut core, time::Duration::from_millis(2000), || clusters[higher].connection(&lower_node).is_none());
+	}
+
+	#[test]
+	fn cluster_connects_to_nodes_by_host_name() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6105, 3, |_, config| {
+			for node_address in config.nodes.values_mut() {
+				node_address.0 = "localhost".into();
+			}
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+	}
+
+	#[test]
+	fn active_encryption_session_is_in_sessions_snapshot() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6108, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let _session = clusters[0].client().new_encryption_session(SessionId::default(), 1).unwrap();
+		let snapshot = clusters[0].client().sessions_snapshot();
+		assert_eq!(snapshot.encryption_sessions, vec![SessionId::default()]);
+		assert!(snapshot.decryption_sessions.is_empty());
+	}
+
+	#[test]
+	fn reconnect_interval_grows_after_repeated_connection_attempts() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6111, 2);
+		let connections = &clusters[0].data.connections;
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let initial_interval = time::Duration::from_secs(10);
+
+		// first attempt is allowed, the next one is delayed
+		assert!(connections.start_connecting(&node1, initial_interval));
+		assert!(!connections.start_connecting(&node1, initial_interval));
+
+		// every next attempt doubles the interval
+		for i in 1..4 {
+			connections.backoff.write().get_mut(&node1).unwrap().0 = time::Instant::now();
+			assert!(connections.start_connecting(&node1, initial_interval));
+			assert_eq!(connections.backoff.read()[&node1].1, initial_interval * (1 << i));
+		}
+	}
+
+	#[test]
+	fn connection_is_removed_when_sending_message_fails() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6113, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// close stream to node1 && broadcast message
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		clusters[0].connection(&node1).unwrap().stream.clone().shutdown().unwrap();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		assert_eq!(cluster_view.broadcast(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))), Ok(()));
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn encryption_session_completes_in_cluster_of_single_node() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6116, 1);
+		run_clusters(&clusters);
+
+		let session = clusters[0].client().new_encryption_session(SessionId::default(), 0).unwrap();
+		assert!(session.wait(Some(time::Duration::from_millis(300))).is_ok());
+	}
+
+	#[test]
+	fn new_session_is_rejected_when_sessions_limit_is_reached() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6117, 1, |_, config| config.max_sessions = 1);
+		run_clusters(&clusters);
+
+		let session = clusters[0].client().new_encryption_session(SessionId::default(), 0).unwrap();
+		assert_eq!(clusters[0].client().new_encryption_session(SessionId::from([1u8; 32]), 0).err(), Some(Error::TooManySessions));
+
+		// when active session is dropped, new session could be started
+		drop(session);
+		assert!(clusters[0].client().new_encryption_session(SessionId::from([1u8; 32]), 0).is_ok());
+	}
+
+	#[test]
+	fn sessions_limit_is_not_exceeded_by_concurrent_sessions() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6297, 1, |_, config| config.max_sessions = 2);
+		run_clusters(&clusters);
+
+		let threads: Vec<_> = (0..8u8).map(|i| {
+			let client = clusters[0].client();
+			::std::thread::spawn(move || client.new_encryption_session(SessionId::from([i + 1; 32]), 0))
+		}).collect();
+		let results: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+
+		assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+		assert!(results.iter().filter_map(|r| r.as_ref().err()).all(|e| e == &Error::TooManySessions));
+		assert_eq!(clusters[0].data.sessions.active_sessions_count(), 2);
+	}
+
+	#[test]
+	fn stale_session_is_removed_after_session_timeout() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6118, 1, |_, config| {
+			config.session_timeout = time::Duration::from_millis(100);
+			config.maintain_interval = time::Duration::from_millis(50);
+		});
+
+		let event_sink = Arc::new(RecordingEventSink::default());
+		clusters[0].client().set_event_sink(event_sink.clone());
+
+		// create session && never initialize it
+		let data = clusters[0].data.clone();
+		let cluster_view = Arc::new(ClusterView::new(data.clone(), data.sessions.nodes.read().clone()));
+		data.sessions.new_encryption_session(data.self_key_pair.public().clone(), SessionId::default(), cluster_view).unwrap();
+		assert_eq!(data.sessions.active_encryption_sessions().len(), 1);
+
+		ClusterCore::schedule_maintain(&core.handle(), data.clone());
+		loop_until(&mut core, time::Duration::from_millis(1000), || !event_sink.events.lock().is_empty());
+		assert!(data.sessions.active_encryption_sessions().is_empty());
+		assert_eq!(*event_sink.events.lock(), vec![format!("failed {:?}", SessionId::default())]);
+	}
+
+	#[test]
+	fn maintain_is_stopped_when_cluster_is_dropped() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6298, 1, |_, config| config.maintain_interval = time::Duration::from_millis(10));
+		let data = Arc::downgrade(&clusters[0].data);
+		ClusterCore::schedule_maintain(&core.handle(), clusters[0].data.clone());
+		loop_for(&mut core, time::Duration::from_millis(50));
+
+		drop(clusters);
+		loop_for(&mut core, time::Duration::from_millis(100));
+		assert!(data.upgrade().is_none());
+	}
+
+	#[test]
+	fn stalled_session_is_stopped_and_removed() {
+		let mut core = Core::new().unwrap();
+		let clock = Arc::new(FakeClock::new());
+		let clusters = make_clusters_with_config(&core, 6296, 1, |_, config| {
+			config.clock = clock.clone() as Arc<Clock>;
+			config.maintain_interval = time::Duration::from_millis(10);
+		});
+
+		let event_sink = Arc::new(RecordingEventSink::default());
+		clusters[0].client().set_event_sink(event_sink.clone());
+
+		// create session && never send messages to it
+		let data = clusters[0].data.clone();
+		let cluster_view = Arc::new(ClusterView::new(data.clone(), data.sessions.nodes.read().clone()));
+		data.sessions.new_encryption_session(data.self_key_pair.public().clone(), SessionId::default(), cluster_view).unwrap();
+
+		// session is stalled long before it is stale
+		clock.advance(time::Duration::from_secs(ENCRYPTION_SESSION_TIMEOUT_INTERVAL + 1));
+		ClusterCore::schedule_maintain(&core.handle(), data.clone());
+		loop_until(&mut core, time::Duration::from_millis(1000), || !event_sink.events.lock().is_empty());
+		assert_eq!(*event_sink.events.lock(), vec![format!("failed {:?}", SessionId::default())]);
+
+		// sessions are still accessible after cleanup
+		assert!(data.sessions.active_encryption_sessions().is_empty());
+		let cluster_view = Arc::new(ClusterView::new(data.clone(), data.sessions.nodes.read().clone()));
+		assert!(data.sessions.new_encryption_session(data.self_key_pair.public().clone(), SessionId::default(), cluster_view).is_ok());
+	}
+
+	#[test]
+	fn connection_stats_are_updated_when_messages_are_sent() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6119, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let stats = clusters[0].client().connection_stats()[&node1].clone();
+		assert_eq!(ConnectionStats { uptime: Default::default(), ..stats }, Default::default());
+
+		// every KeepAlive is answered with KeepAliveResponse
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		for nonce in 1..4 {
+			cluster_view.send(&node1, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: nonce }))).unwrap();
+		}
+		loop_until(&mut core, time::Duration::from_millis(300), || {
+			let stats = clusters[0].client().connection_stats()[&node1].clone();
+			stats.messages_sent == 3 && stats.messages_received == 3
+		});
+		let stats = clusters[0].client().connection_stats()[&node1].clone();
+		assert!(stats.bytes_sent > 0);
+		assert!(stats.bytes_received > 0);
+	}
+
+	#[test]
+	fn node_added_to_running_cluster_is_connected() {
+		let mut core = Core::new().unwrap();
+		// first 3 nodes are unaware of the 4th node
+		let clusters = make_clusters_with_config(&core, 6121, 4, |i, config| if i < 3 {
+			config.nodes = config.nodes.iter()
+				.filter(|&(_, node_address)| node_address.1 != 6124)
+				.map(|(node_id, node_address)| (node_id.clone(), node_address.clone()))
+				.collect();
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0..3].iter().all(all_connections_established));
+
+		let new_node = clusters[3].config().self_key_pair.public().clone();
+		assert!(clusters[0..3].iter().all(|c| c.connection(&new_node).is_none()));
+
+		for cluster in &clusters[0..3] {
+			cluster.client().add_node(new_node.clone(), ("127.0.0.1".into(), 6124)).unwrap();
+		}
+		clusters[3].client().connect();
+		loop_until(&mut core, time::Duration::from_millis(300), || all_connections_established(&clusters[3])
+			&& clusters[0..3].iter().all(|c| c.connection(&new_node).is_some()));
+	}
+
+	#[test]
+	fn node_with_invalid_address_is_not_added() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6293, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let new_node = Random.generate().unwrap().public().clone();
+		match clusters[0].client().add_node(new_node.clone(), ("127.0.0.1".into(), 6293)) {
+			Err(Error::InvalidConfiguration(_)) => (),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+		match clusters[0].client().add_node(new_node.clone(), ("".into(), 6295)) {
+			Err(Error::InvalidConfiguration(_)) => (),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+		assert!(!clusters[0].client().configured_nodes().contains_key(&new_node));
+
+		// connected node is not reconnected when added again
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let connection = clusters[0].connection(&node1).unwrap();
+		clusters[0].client().add_node(node1.clone(), ("127.0.0.1".into(), 6294)).unwrap();
+		loop_for(&mut core, time::Duration::from_millis(50));
+		assert!(Arc::ptr_eq(&clusters[0].connection(&node1).unwrap(), &connection));
+	}
+
+	#[test]
+	fn removed_node_is_disconnected() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6125, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node2 = clusters[2].config().self_key_pair.public().clone();
+		clusters[0].client().remove_node(&node2);
+		assert!(clusters[0].connection(&node2).is_none());
+		assert!(clusters[0].data.connections.disconnected_nodes().is_empty());
+	}
+
+	#[test]
+	fn node_sending_too_large_message_is_blacklisted() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6128, 2, |i, config| if i == 1 {
+			config.max_message_size = 16;
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// encrypted KeepAlive message is definitely larger than 16 bytes
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		// session on node1, which waits for messages from node0
+		let node1_view = Arc::new(ClusterView::new(clusters[1].data.clone(), clusters[1].data.connections.connected_nodes()));
+		let session = clusters[1].data.sessions.new_encryption_session(node0.clone(), SessionId::default(), node1_view).unwrap();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		cluster_view.send(&node1, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].connection(&node0).is_none());
+		assert!(clusters[1].data.connections.is_blacklisted(&node0));
+		// sessions, involving node0, are failed
+		assert_eq!(session.state(), EncryptionSessionState::Failed);
+		assert_eq!(clusters[1].data.sessions.active_sessions_count(), 0);
+	}
+
+	#[test]
+	fn dummy_cluster_separates_broadcasted_and_direct_messages() {
+		let nodes: Vec<NodeId> = (0..3).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let cluster = DummyCluster::new(nodes[0].clone());
+		cluster.add_node(nodes[1].clone());
+		cluster.add_node(nodes[2].clone());
+
+		let is_keep_alive = |message: &Message| match *message {
+			Message::Cluster(ClusterMessage::KeepAlive(_)) => true,
+			_ => false,
+		};
+		cluster.send(&nodes[1], Message::Cluster(ClusterMessage::KeepAliveResponse(message::KeepAliveResponse {}))).unwrap();
+		cluster.broadcast(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))).unwrap();
+
+		let sent_to_node1 = cluster.sent_to(&nodes[1]);
+		assert_eq!(sent_to_node1.len(), 2);
+		assert!(!is_keep_alive(&sent_to_node1[0]) && is_keep_alive(&sent_to_node1[1]));
+		let sent_to_node2 = cluster.sent_to(&nodes[2]);
+		assert_eq!(sent_to_node2.len(), 1);
+		assert!(is_keep_alive(&sent_to_node2[0]));
+
+		assert!(cluster.take_broadcast().map(|message| is_keep_alive(&message)).unwrap_or(false));
+		assert!(cluster.take_broadcast().is_none());
+		let (to, message) = cluster.take_direct().unwrap();
+		assert!(to == nodes[1] && !is_keep_alive(&message));
+		assert!(cluster.take_direct().is_none());
+	}
+
+	#[test]
+	fn dummy_cluster_drains_messages_in_fifo_order() {
+		let nodes: Vec<NodeId> = (0..3).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let cluster = DummyCluster::new(nodes[0].clone());
+		cluster.add_node(nodes[1].clone());
+		cluster.add_node(nodes[2].clone());
+
+		let ping = |nonce| Message::Cluster(ClusterMessage::Ping(message::Ping { nonce: nonce }));
+		cluster.send(&nodes[2], ping(0)).unwrap();
+		cluster.broadcast(ping(1)).unwrap();
+		cluster.send(&nodes[1], ping(2)).unwrap();
+		assert_eq!(cluster.message_count(), 4);
+
+		let messages: Vec<_> = cluster.drain_messages().into_iter()
+			.map(|(to, message)| match message {
+				Message::Cluster(ClusterMessage::Ping(ping)) => (to, ping.nonce),
+				_ => unreachable!("only pings are sent"),
+			})
+			.collect();
+		assert_eq!(messages, vec![
+			(nodes[2].clone(), 0),
+			(nodes[1].clone(), 1),
+			(nodes[2].clone(), 1),
+			(nodes[1].clone(), 2),
+		]);
+		assert_eq!(cluster.message_count(), 0);
+		assert!(cluster.drain_messages().is_empty());
+	}
+
+	#[test]
+	fn wait_for_message_skips_non_matching_messages() {
+		let mut core = Core::new().unwrap();
+		let nodes: Vec<NodeId> = (0..2).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let cluster = DummyCluster::new(nodes[0].clone());
+		cluster.add_node(nodes[1].clone());
+		cluster.broadcast(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))).unwrap();
+		cluster.send(&nodes[1], Message::Cluster(ClusterMessage::KeepAliveResponse(message::KeepAliveResponse {}))).unwrap();
+
+		let (to, _) = wait_for_message(&mut core, &cluster, time::Duration::from_millis(100), |message| match *message {
+			Message::Cluster(ClusterMessage::KeepAliveResponse(_)) => true,
+			_ => false,
+		});
+		assert_eq!(to, nodes[1]);
+		assert!(cluster.take_message().is_none());
+	}
+
+	#[test]
+	fn nodelay_is_set_on_established_connections() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6130, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		assert!(clusters[0].connection(&node1).unwrap().stream.nodelay().unwrap());
+		assert!(clusters[1].connection(&node0).unwrap().stream.nodelay().unwrap());
+	}
+
+	#[test]
+	fn dummy_cluster_does_not_send_to_excluded_node() {
+		let nodes: Vec<NodeId> = (0..3).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let cluster = DummyCluster::new(nodes[0].clone());
+		cluster.add_node(nodes[1].clone());
+		cluster.add_node(nodes[2].clone());
+		cluster.broadcast_except(&nodes[1], Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))).unwrap();
+
+		assert!(cluster.sent_to(&nodes[1]).is_empty());
+		assert_eq!(cluster.sent_to(&nodes[2]).len(), 1);
+		assert_eq!(cluster.take_message().map(|(to, _)| to), Some(nodes[2].clone()));
+		assert!(cluster.take_message().is_none());
+	}
+
+	#[test]
+	fn cluster_is_not_created_when_other_node_has_listen_address() {
+		let core = Core::new().unwrap();
+		let key_pairs: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+		let config = ClusterConfiguration::new(
+			1,
+			key_pairs[0].clone(),
+			("127.0.0.1".to_owned(), 6132),
+			key_pairs.iter().map(|kp| (kp.public().clone(), ("127.0.0.1".into(), 6132))).collect(),
+			false,
+			Arc::new(DummyKeyStorage::default()),
+			Arc::new(DummyAclStorage::default()),
+		);
+
+		match ClusterCore::new(core.handle(), config).err() {
+			Some(Error::InvalidConfiguration(ref description)) => assert!(description.contains("127.0.0.1:6132")),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+	}
+
+	#[test]
+	fn cluster_is_not_created_when_this_node_is_listed_at_other_address() {
+		let core = Core::new().unwrap();
+		let key_pairs: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+		let config = ClusterConfiguration::new(
+			1,
+			key_pairs[0].clone(),
+			("127.0.0.1".to_owned(), 6231),
+			key_pairs.iter().enumerate().map(|(i, kp)| (kp.public().clone(), ("127.0.0.1".into(), 6232 + i as u16))).collect(),
+			false,
+			Arc::new(DummyKeyStorage::default()),
+			Arc::new(DummyAclStorage::default()),
+		);
+
+		match ClusterCore::new(core.handle(), config).err() {
+			Some(Error::InvalidConfiguration(ref description)) => assert!(description.contains("this node is listed at 127.0.0.1:6232")),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+	}
+
+	#[test]
+	fn is_connected_returns_true_when_connection_is_established() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6133, 2);
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		assert!(!clusters[0].client().is_connected(&node1));
+
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+		assert!(clusters[0].client().is_connected(&node1));
+	}
+
+	#[test]
+	fn node_is_not_connected_twice_simultaneously() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6135, 2);
+		run_clusters(&clusters);
+
+		// the 'lower' node has started connecting to the 'higher' node
+		let (lower, higher) = if clusters[0].config().self_key_pair.public() < clusters[1].config().self_key_pair.public() { (0, 1) } else { (1, 0) };
+		let higher_node = clusters[higher].config().self_key_pair.public().clone();
+		assert!(clusters[lower].data.connections.pending_connects.lock().contains(&higher_node));
+
+		// even when reconnect interval is ignored, new attempt is not started
+		clusters[lower].data.connections.backoff.write().clear();
+		assert!(!clusters[lower].data.connections.start_connecting(&higher_node, time::Duration::from_secs(1)));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+		assert!(clusters[lower].data.connections.pending_connects.lock().is_empty());
+	}
+
+	#[test]
+	fn connection_attempt_fails_after_handshake_timeout() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6137, 2, |_, config| {
+			config.allow_connecting_to_higher_nodes = true;
+			config.initial_connect_delay = time::Duration::from_millis(0);
+			config.handshake_timeout = time::Duration::from_millis(50);
+		});
+		// node1 accepts TCP connections, but never responds to handshake
+		let _listener = ::std::net::TcpListener::bind("127.0.0.1:6138").unwrap();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		clusters[0].run_connections().unwrap();
+		assert!(clusters[0].data.connections.pending_connects.lock().contains(&node1));
+
+		loop_until(&mut core, time::Duration::from_millis(1000), || clusters[0].data.connections.pending_connects.lock().is_empty());
+		assert!(clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn connection_address_is_redacted_when_configured() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6139, 2, |i, config| config.redact_node_addresses = i == 0);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let redacted = format!("{}", clusters[0].connection(&node1).unwrap());
+		assert!(redacted.contains("@*:") && !redacted.contains("127.0.0.1"));
+		let not_redacted = format!("{}", clusters[1].connection(&node0).unwrap());
+		assert!(not_redacted.contains("@127.0.0.1:"));
+	}
+
+	#[test]
+	fn session_started_and_failed_events_are_reported() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6141, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let event_sink = Arc::new(RecordingEventSink::default());
+		clusters[1].client().set_event_sink(event_sink.clone());
+
+		// initialize session with invalid derived point
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		cluster_view.send(&node1, Message::Encryption(EncryptionMessage::InitializeSession(message::InitializeSession {
+			session: SessionId::default().into(),
+			derived_point: Public::default().into(),
+		}))).unwrap();
+
+		let session_id = SessionId::default();
+		loop_until(&mut core, time::Duration::from_millis(300), || event_sink.events.lock().len() == 2);
+		assert_eq!(*event_sink.events.lock(), vec![format!("started {:?}", session_id), format!("failed {:?}", session_id)]);
+	}
+
+	#[test]
+	fn failed_and_panicked_spawned_futures_do_not_stop_event_loop() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6243, 1);
+		run_clusters(&clusters);
+
+		let data = clusters[0].data.clone();
+		data.spawn(lazy(|| failed::<(), _>("error")));
+		data.spawn(lazy(|| -> Result<(), ()> { panic!("panic") }));
+
+		let flag = Arc::new(AtomicBool::new(false));
+		let flag_to_set = flag.clone();
+		data.spawn(lazy(move || {
+			flag_to_set.store(true, Ordering::SeqCst);
+			finished::<(), ()>(())
+		}));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || flag.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn aborted_session_is_removed_and_participants_are_notified() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6149, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let event_sink = Arc::new(RecordingEventSink::default());
+		clusters[1].client().set_event_sink(event_sink.clone());
+
+		// start session && abort it before it has a chance to complete
+		let session_id = SessionId::default();
+		let session = clusters[0].client().new_encryption_session(session_id.clone(), 1).unwrap();
+		assert_eq!(clusters[0].client().sessions_snapshot().encryption_sessions, vec![session_id.clone()]);
+		clusters[0].client().abort_session(&session_id).unwrap();
+		assert!(clusters[0].client().sessions_snapshot().encryption_sessions.is_empty());
+		assert!(session.wait(Some(time::Duration::from_millis(0))).is_err());
+		assert_eq!(clusters[0].client().abort_session(&session_id), Err(Error::InvalidSessionId));
+
+		// participant receives SessionError && removes its own session
+		loop_until(&mut core, time::Duration::from_millis(300), || event_sink.events.lock().contains(&format!("failed {:?}", session_id)));
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].client().sessions_snapshot().encryption_sessions.is_empty());
+	}
+
+	#[test]
+	fn failed_connection_attempt_is_counted_in_metrics() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6151, 2, |_, config| {
+			config.allow_connecting_to_higher_nodes = true;
+			config.initial_connect_delay = time::Duration::from_millis(0);
+		});
+		// second node is never started => its address is unreachable
+		clusters[0].run().unwrap();
+
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0].client().connection_metrics().outbound.connects_failed != 0);
+		let metrics = clusters[0].client().connection_metrics();
+		assert_eq!(metrics.outbound.connects_succeeded, 0);
+		assert_eq!(metrics.inbound, Default::default());
+	}
+
+	#[test]
+	fn listen_only_node_is_connected_by_other_node() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6153, 2, |i, config| {
+			config.allow_connecting_to_higher_nodes = true;
+			config.initial_connect_delay = time::Duration::from_millis(0);
+			config.connect_outbound = i != 0;
+		});
+		clusters[0].run().unwrap();
+		loop_for(&mut core, time::Duration::from_millis(100));
+		assert_eq!(clusters[0].client().connection_metrics().outbound, Default::default());
+
+		clusters[1].run().unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+		assert_eq!(clusters[0].client().connection_metrics().outbound, Default::default());
+		assert_eq!(clusters[0].client().connection_metrics().inbound.connects_succeeded, 1);
+	}
+
+	#[test]
+	fn encryption_session_future_is_resolved_when_session_is_completed() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6155, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let result = Arc::new(Mutex::new(None));
+		let session_result = result.clone();
+		core.handle().spawn(clusters[0].client().new_encryption_session_future(SessionId::default(), 1)
+			.then(move |r| { *session_result.lock() = Some(r); finished(()) }));
+
+		loop_until(&mut core, time::Duration::from_millis(1000), || result.lock().is_some());
+		assert!(result.lock().take().unwrap().is_ok());
+		assert!(clusters[0].client().sessions_snapshot().encryption_sessions.is_empty());
+	}
+
+	#[test]
+	fn message_with_other_node_id_is_rejected() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6158, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		cluster_view.send(&node1, Message::Cluster(ClusterMessage::NodePublicKey(message::NodePublicKey {
+			node_id: Random.generate().unwrap().public().clone().into(),
+			confirmation_plain: SessionId::default().into(),
+		}))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].client().connection_stats()[&node0].messages_rejected == 1);
+
+		// message with the same node id is not rejected
+		cluster_view.send(&node1, Message::Cluster(ClusterMessage::NodePublicKey(message::NodePublicKey {
+			node_id: node0.clone().into(),
+			confirmation_plain: SessionId::default().into(),
+		}))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].client().connection_stats()[&node0].messages_received == 2);
+		assert_eq!(clusters[1].client().connection_stats()[&node0].messages_rejected, 1);
+	}
+
+	#[test]
+	fn broadcast_is_delivered_to_connected_nodes_when_some_nodes_are_disconnected() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6160, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let disconnected_node = Random.generate().unwrap().public().clone();
+		let mut nodes = clusters[0].data.connections.connected_nodes();
+		nodes.insert(node0.clone());
+		nodes.insert(disconnected_node.clone());
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), nodes);
+		assert_eq!(cluster_view.broadcast(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))),
+			Err(Error::NodesDisconnected(vec![disconnected_node].into_iter().collect())));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1..].iter()
+			.all(cluster| cluster.client().connection_stats()[&node0].messages_received == 1));
+	}
+
+	#[test]
+	fn disconnect_message_closes_connection_on_both_sides() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6163, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		clusters[0].client().remove_node(&node1);
+		assert!(!clusters[0].client().is_connected(&node1));
+		loop_until(&mut core, time::Duration::from_millis(300), || !clusters[1].client().is_connected(&node0));
+	}
+
+	#[test]
+	fn clusters_are_working_over_shared_thread_pool() {
+		let mut core = Core::new().unwrap();
+		let pool = CpuPool::new(1);
+		let clusters: Vec<_> = make_clusters_configs(6165, 3, |_, _| ()).into_iter()
+			.map(|params| ClusterCore::new_with_pool(core.handle(), params, pool.clone()).unwrap())
+			.collect();
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let session = clusters[0].client().new_encryption_session(SessionId::default(), 1).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(1000), || session.state() == EncryptionSessionState::Finished);
+	}
+
+	#[test]
+	fn reading_from_closed_connection_fails() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6168, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let connection = clusters[0].connection(&node1).unwrap();
+		connection.close();
+		assert!(core.run(connection.read_header(clusters[0].config().max_message_size)).is_err());
+	}
+
+	#[test]
+	fn node_is_not_ready_when_quorum_is_lost() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6170, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let health = clusters[0].client().health();
+		assert!(health.is_ready());
+		assert_eq!(health.connected_nodes, 2);
+		assert_eq!(health.configured_nodes, 2);
+		assert_eq!(health.active_sessions, 0);
+		assert_eq!(health.oldest_session_age, None);
+
+		for connection in clusters[0].data.connections.active_connections() {
+			clusters[0].data.connections.remove(connection.node_id(), connection.is_inbound());
+		}
+		let health = clusters[0].client().health();
+		assert!(!health.is_ready());
+		assert_eq!(health.connected_nodes, 0);
+	}
+
+	#[test]
+	fn connection_is_closed_after_too_many_malformed_messages() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6173, 2, |_, config| config.max_protocol_errors = 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// message of unknown kind with payload, which can't be decrypted
+		let malformed_message = |count: usize| {
+			let mut data = Vec::new();
+			for _ in 0..count {
+				data.extend_from_slice(&[1, 255, 32, 0]);
+				data.extend_from_slice(&[0u8; 32]);
+			}
+			data
+		};
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let stream = clusters[0].connection(&node1).unwrap().stream.clone();
+		core.run(write_all(stream.clone(), malformed_message(2))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].connection(&node0).unwrap().counters.protocol_errors.load(Ordering::SeqCst) == 2);
+
+		// valid message resets the counter
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), clusters[0].data.connections.connected_nodes());
+		cluster_view.send(&node1, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].connection(&node0).unwrap().counters.protocol_errors.load(Ordering::SeqCst) == 0);
+
+		core.run(write_all(stream, malformed_message(3))).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].connection(&node0).is_none());
+	}
+
+	#[test]
+	fn ping_measures_round_trip_time() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6175, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let rtt = Arc::new(Mutex::new(None));
+		let ping_rtt = rtt.clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		core.handle().spawn(clusters[0].client().ping(&node1)
+			.then(move |r| { *ping_rtt.lock() = Some(r); finished(()) }));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || rtt.lock().is_some());
+		let rtt = rtt.lock().take().unwrap().unwrap();
+		assert!(rtt > time::Duration::from_millis(0));
+		assert!(rtt < time::Duration::from_millis(300));
+		assert!(clusters[0].data.pending_pings.lock().is_empty());
+	}
+
+	#[test]
+	fn ping_fails_when_pong_is_not_received_in_time() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6290, 2, |_, config| config.ping_timeout = time::Duration::from_millis(50));
+
+		// peer accepts connection, but never responds
+		let listener = ::std::net::TcpListener::bind("127.0.0.1:6292").unwrap();
+		let peer_address = listener.local_addr().unwrap();
+		let stream = ::std::net::TcpStream::connect(&peer_address).unwrap();
+		let _peer_stream = listener.accept().unwrap();
+		let stream = TcpStream::from_stream(stream, &core.handle()).unwrap();
+
+		let node1 = clusters[1].client().self_node_id();
+		let connection = Connection::from_parts(node1.clone(), peer_address, false, stream.into());
+		assert!(clusters[0].data.connections.insert(connection));
+
+		let result = Arc::new(Mutex::new(None));
+		let ping_result = result.clone();
+		core.handle().spawn(clusters[0].client().ping(&node1)
+			.then(move |r| { *ping_result.lock() = Some(r); finished(()) }));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || result.lock().is_some());
+		assert_eq!(result.lock().take().unwrap(), Err(Error::PingTimeout));
+		assert!(clusters[0].data.pending_pings.lock().is_empty());
+	}
+
+	#[test]
+	fn detailed_broadcast_reports_delivery_status_for_every_node() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6177, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let node2 = clusters[2].config().self_key_pair.public().clone();
+		let mut nodes = clusters[0].data.connections.connected_nodes();
+		nodes.insert(node0);
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), nodes);
+		let connection = clusters[0].connection(&node2).unwrap();
+		clusters[0].data.connections.remove(&node2, connection.is_inbound());
+
+		let results = cluster_view.broadcast_detailed(Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 })));
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[&node1], Ok(()));
+		assert_eq!(results[&node2], Err(Error::NodeDisconnected));
+	}
+
+	#[test]
+	fn encryption_session_with_too_large_threshold_is_rejected_immediately() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6180, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		match clusters[0].client().new_encryption_session(SessionId::default(), 5) {
+			Err(Error::InvalidThreshold) => (),
+			Err(e) => panic!("unexpected error {:?}", e),
+			_ => panic!("unexpected success"),
+		}
+		assert!(clusters[0].client().sessions_snapshot().encryption_sessions.is_empty());
+	}
+
+	#[test]
+	fn connection_uptime_grows() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6183, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let connection = clusters[0].connection(&node1).unwrap();
+		let uptime1 = connection.uptime();
+		loop_for(&mut core, time::Duration::from_millis(10));
+		let uptime2 = connection.uptime();
+		loop_for(&mut core, time::Duration::from_millis(10));
+		let uptime3 = clusters[0].client().connection_stats()[&node1].uptime;
+		assert!(uptime1 < uptime2 && uptime2 < uptime3);
+		assert!(clusters[0].client().health().shortest_connection_uptime.unwrap() >= uptime3);
+	}
+
+	#[test]
+	fn registered_session_timeout_stops_session() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6185, 1);
+		run_clusters(&clusters);
+
+		// session is never initialized => it will stay active until timeout
+		let data = clusters[0].data.clone();
+		let session_id = SessionId::default();
+		let nodes = vec![clusters[0].config().self_key_pair.public().clone()].into_iter().collect();
+		let cluster_view = Arc::new(ClusterView::new(data.clone(), nodes));
+		let session = data.sessions.new_encryption_session(data.self_key_pair.public().clone(), session_id.clone(), cluster_view).unwrap();
+		ClusterData::register_session_timeout(&data, session_id.clone(), time::Duration::from_millis(10));
+
+		loop_until(&mut core, time::Duration::from_millis(300), || data.sessions.encryption_session(&session_id).is_none());
+		assert_eq!(session.state(), EncryptionSessionState::Failed);
+	}
+
+	#[test]
+	fn excess_inbound_connections_are_closed() {
+		use std::io::{Read, ErrorKind};
+		use std::net::TcpStream;
+
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6186, 1, |_, config| config.max_inbound_connections = 1);
+		run_clusters(&clusters);
+
+		// open raw connections, which never complete handshake
+		let streams: Vec<_> = (0..3).map(|_| TcpStream::connect("127.0.0.1:6186").unwrap()).collect();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0].data.connections.inbound_connections_count() == 1);
+		loop_for(&mut core, time::Duration::from_millis(50));
+
+		let closed = streams.into_iter().filter(|stream| {
+			stream.set_nonblocking(true).unwrap();
+			let mut buffer = [0u8; 1024];
+			match (&*stream).read(&mut buffer) {
+				Ok(0) => true,
+				Err(ref err) if err.kind() == ErrorKind::ConnectionReset => true,
+				_ => false,
+			}
+		}).count();
+		assert_eq!(closed, 2);
+		assert_eq!(clusters[0].data.connections.inbound_connections_count(), 1);
+	}
+
+	#[test]
+	fn new_sessions_are_rejected_when_not_accepting_sessions() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6187, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// paused node refuses to start new session
+		clusters[1].client().set_accepting_sessions(false);
+		assert_eq!(clusters[1].client().new_encryption_session(SessionId::default(), 1).err(), Some(Error::NotAcceptingSessions));
+
+		// paused node refuses to join new session
+		let session = clusters[0].client().new_encryption_session(SessionId::default(), 1).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || session.joint_public_key().is_some());
+		assert!(session.joint_public_key().unwrap().is_err());
+		assert!(clusters[1].client().encryption_session(&SessionId::default()).is_none());
+
+		// resumed node is accepting sessions again
+		clusters[1].client().set_accepting_sessions(true);
+		let session = clusters[0].client().new_encryption_session(SessionId::from([1u8; 32]), 1).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || session.state() == EncryptionSessionState::Finished);
+		assert!(session.joint_public_key().unwrap().is_ok());
+	}
+
+	#[test]
+	fn encryption_session_runs_on_subset_of_nodes() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6244, 4);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let received_messages = |i: usize| clusters[i].client().connection_stats().values().map(|s| s.messages_received).sum::<usize>();
+		let received_by_uninvolved = (received_messages(2), received_messages(3));
+
+		// run session on nodes 0 && 1
+		let nodes = vec![clusters[0].data.self_key_pair.public().clone(), clusters[1].data.self_key_pair.public().clone()].into_iter().collect();
+		let session = clusters[0].client().new_encryption_session_with_nodes(SessionId::default(), 1, nodes).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || session.state() == EncryptionSessionState::Finished
+			&& clusters[1].client().encryption_session(&SessionId::default()).is_none());
+		assert!(session.joint_public_key().unwrap().is_ok());
+		loop_for(&mut core, time::Duration::from_millis(50));
+
+		// uninvolved nodes have received nothing
+		assert!(clusters[1].config().key_storage.contains(&SessionId::default()));
+		assert!(!clusters[2].config().key_storage.contains(&SessionId::default()));
+		assert_eq!((received_messages(2), received_messages(3)), received_by_uninvolved);
+	}
+
+	#[test]
+	fn encryption_session_runs_on_subset_of_nodes_when_other_node_is_stopped() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6240, 3);
+		// node 2 is never started
+		run_clusters(&clusters[..2]);
+		let node0 = clusters[0].data.self_key_pair.public().clone();
+		let node1 = clusters[1].data.self_key_pair.public().clone();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0].client().is_connected(&node1)
+			&& clusters[1].client().is_connected(&node0));
+
+		let nodes = vec![node0, node1].into_iter().collect();
+		let session = clusters[0].client().new_encryption_session_with_nodes(SessionId::default(), 1, nodes).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || session.state() == EncryptionSessionState::Finished
+			&& clusters[1].client().encryption_session(&SessionId::default()).is_none());
+		assert!(session.joint_public_key().unwrap().is_ok());
+		assert!(clusters[1].config().key_storage.contains(&SessionId::default()));
+	}
+
+	#[test]
+	fn encryption_session_on_subset_of_nodes_requires_self() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6192, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let nodes = vec![clusters[1].data.self_key_pair.public().clone()].into_iter().collect();
+		assert_eq!(clusters[0].client().new_encryption_session_with_nodes(SessionId::default(), 0, nodes).err(), Some(Error::InvalidNodesConfiguration));
+	}
+
+	#[test]
+	fn connection_is_closed_when_message_read_stalls() {
+		let mut core = Core::new().unwrap();
+		// stalled read is detected without waiting for maintain procedures
+		let clusters = make_clusters_with_config(&core, 6248, 2, |_, config| config.read_timeout = time::Duration::from_millis(50));
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// send header of message with 32-bytes payload && never send the payload
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let stream = clusters[0].connection(&node1).unwrap().stream.clone();
+		core.run(write_all(stream, vec![1, 3, 32, 0])).unwrap();
+		let connection = clusters[1].connection(&node0).unwrap();
+		loop_until(&mut core, time::Duration::from_millis(300), || connection.pending_read_duration().is_some());
+
+		// node could reconnect after stalled connection is closed => compare connections
+		let is_same_connection = |c: Arc<Connection>| &*c as *const Connection == &*connection as *const Connection;
+		loop_until(&mut core, time::Duration::from_millis(300), || !clusters[1].connection(&node0).map(&is_same_connection).unwrap_or(false));
+		assert!(connection.pending_read_duration().unwrap() >= time::Duration::from_millis(50));
+	}
+
+	#[test]
+	fn cloned_configuration_is_independent() {
+		let config = make_clusters_configs(6195, 1, |_, _| ()).pop().unwrap();
+		let mut cloned_config = config.clone();
+		cloned_config.listen_address.1 = 6196;
+		cloned_config.max_sessions = 1;
+
+		assert_eq!(config.listen_address.1, 6195);
+		assert_eq!(config.max_sessions, MAX_ACTIVE_SESSIONS);
+		assert_eq!(cloned_config.self_key_pair.public(), config.self_key_pair.public());
+		assert_eq!(cloned_config.nodes, config.nodes);
+	}
+
+	#[test]
+	fn node_is_connected_using_advertised_address() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6197, 2, |i, config| {
+			config.allow_connecting_to_higher_nodes = true;
+			config.initial_connect_delay = time::Duration::from_millis(0);
+			config.connect_outbound = i == 0;
+			if i == 0 {
+				// nobody is listening at the listed address of node1
+				let node1 = config.nodes.keys().cloned().find(|n| n != config.self_key_pair.public()).unwrap();
+				config.nodes.insert(node1.clone(), ("127.0.0.1".into(), 6299));
+				config.advertised_addresses.insert(node1, ("127.0.0.1".into(), 6198));
+			}
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+	}
+
+	#[test]
+	fn advertised_address_of_unknown_node_is_rejected() {
+		let core = Core::new().unwrap();
+		let mut config = make_clusters_configs(6199, 1, |_, _| ()).pop().unwrap();
+		config.advertised_addresses.insert(Random.generate().unwrap().public().clone(), ("127.0.0.1".into(), 6200));
+		match ClusterCore::new(core.handle(), config) {
+			Err(Error::InvalidConfiguration(_)) => (),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+
+		let mut config = make_clusters_configs(6199, 1, |_, _| ()).pop().unwrap();
+		let self_node_id = config.self_key_pair.public().clone();
+		config.advertised_addresses.insert(self_node_id, ("".into(), 6200));
+		match ClusterCore::new(core.handle(), config) {
+			Err(Error::InvalidConfiguration(_)) => (),
+			_ => panic!("expected InvalidConfiguration error"),
+		}
+	}
+
+	#[test]
+	fn configured_nodes_are_reported() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6201, 3);
+
+		let self_node_id = clusters[0].config().self_key_pair.public().clone();
+		let mut expected_nodes = clusters[0].config().nodes.clone();
+		expected_nodes.remove(&self_node_id);
+		assert_eq!(clusters[0].client().self_node_id(), self_node_id);
+		assert_eq!(clusters[0].client().configured_nodes(), expected_nodes);
+		assert_eq!(clusters[0].client().configured_nodes().len(), 2);
+	}
+
+	#[test]
+	fn duplicate_connections_are_not_established_on_start() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6250, 3, |_, config| {
+			config.allow_connecting_to_higher_nodes = true;
+			config.initial_connect_delay = time::Duration::from_millis(100);
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// wait for all delayed connection attempts
+		loop_for(&mut core, time::Duration::from_millis(150));
+		let duplicates_dropped: usize = clusters.iter()
+			.map(|cluster| cluster.client().connection_metrics())
+			.map(|metrics| metrics.inbound.duplicates_dropped + metrics.outbound.duplicates_dropped)
+			.sum();
+		// delay is random => some delayed attempt could still race with connection from lower node
+		// but without delays duplicate connection is dropped on both sides of every pair of nodes
+		assert!(duplicates_dropped < 2 * 3);
+		assert!(clusters.iter().all(all_connections_established));
+	}
+
+	#[test]
+	fn session_info_is_reported_for_active_session() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6253, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		assert_eq!(clusters[0].client().session_info(&SessionId::default()), None);
+		let _session = clusters[0].client().new_encryption_session(SessionId::default(), 1).unwrap();
+		let session_info = clusters[0].client().session_info(&SessionId::default()).unwrap();
+		assert_eq!(session_info.threshold, 1);
+		assert_eq!(session_info.master, clusters[0].client().self_node_id());
+		assert_eq!(session_info.nodes, clusters.iter().map(|c| c.client().self_node_id()).collect::<BTreeSet<_>>());
+	}
+
+	#[test]
+	fn wait_for_quorum_resolves_when_enough_nodes_are_connected() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6256, 3);
+		let quorum = clusters[0].client().wait_for_quorum(2, time::Duration::from_millis(1000));
+		run_clusters(&clusters);
+		assert_eq!(core.run(quorum), Ok(()));
+		assert!(clusters[0].client().health().connected_nodes >= 2);
+
+		// already reached quorum is reported immediately
+		assert_eq!(clusters[0].client().wait_for_quorum(2, time::Duration::from_millis(0)).wait(), Ok(()));
+	}
+
+	#[test]
+	fn wait_for_quorum_fails_on_timeout() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6259, 3);
+		let quorum = clusters[0].client().wait_for_quorum(2, time::Duration::from_millis(100));
+		assert_eq!(core.run(quorum), Err(Error::QuorumTimeout));
+		assert!(clusters[0].data.quorum_waiters.lock().is_empty());
+	}
+
+	#[test]
+	fn nodes_are_ordered_using_configured_order() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6262, 4, |_, config| {
+			let mut nodes_order: Vec<_> = config.nodes.keys().cloned().collect();
+			nodes_order.truncate(2);
+			nodes_order.reverse();
+			config.nodes_order = nodes_order;
+		});
+
+		// listed nodes go first, in configured order, followed by other nodes in order of their ids
+		let nodes: Vec<_> = clusters[0].data.config.nodes.keys().cloned().collect();
+		let expected_order = vec![nodes[1].clone(), nodes[0].clone(), nodes[2].clone(), nodes[3].clone()];
+		assert_eq!(clusters[0].data.ordered_nodes(nodes.iter()), expected_order);
+		assert_eq!(clusters[0].data.ordered_nodes(nodes.iter().rev()), expected_order);
+	}
+
+	#[test]
+	fn maintain_continues_after_panic_in_maintain_procedure() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6266, 2, |_, config| config.maintain_interval = time::Duration::from_millis(50));
+		let maintains_count = Arc::new(AtomicUsize::new(0));
+		let hook_maintains_count = maintains_count.clone();
+		*clusters[0].data.maintain_hook.lock() = Some(Box::new(move || {
+			if hook_maintains_count.fetch_add(1, Ordering::Relaxed) == 0 {
+				panic!("maintain hook panic");
+			}
+		}));
+		run_clusters(&clusters);
+
+		// maintain is still executed after first (panicked) tick
+		loop_until(&mut core, time::Duration::from_millis(500), || maintains_count.load(Ordering::Relaxed) >= 3);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+	}
+
+	#[test]
+	fn listener_is_rebound_while_previous_connection_is_in_time_wait() {
+		use std::io::Read;
+
+		let listen_address: SocketAddr = "127.0.0.1:6268".parse().unwrap();
+		let listener = make_listener(&listen_address, 16).unwrap();
+		let mut client = ::std::net::TcpStream::connect(&listen_address).unwrap();
+		let (server, _) = listener.accept().unwrap();
+
+		// closing connection on listener side leaves listen port in TIME_WAIT state
+		drop(server);
+		let mut buffer = [0u8; 1];
+		assert_eq!(client.read(&mut buffer).unwrap(), 0);
+		drop(client);
+		drop(listener);
+
+		assert!(make_listener(&listen_address, 16).is_ok());
+	}
+
+	#[test]
+	fn listener_is_bound_to_ipv6_address() {
+		let listen_address: SocketAddr = "[::1]:6269".parse().unwrap();
+		// IPv6 could be disabled on test machine
+		if ::std::net::TcpListener::bind(&listen_address).is_ok() {
+			assert!(make_listener(&listen_address, 16).is_ok());
+		}
+	}
+
+	#[test]
+	fn participant_removes_session_when_master_reports_completion() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6211, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// start session on participant node1, mastered by node0
+		let session_id = SessionId::default();
+		let node0 = clusters[0].client().self_node_id();
+		let node1 = clusters[1].client().self_node_id();
+		let mut nodes = clusters[1].data.connections.connected_nodes();
+		nodes.insert(node1.clone());
+		let cluster_view = Arc::new(ClusterView::new(clusters[1].data.clone(), nodes));
+		clusters[1].data.sessions.new_encryption_session(node0, session_id.clone(), cluster_view).unwrap();
+		let session_completed = || Message::Cluster(ClusterMessage::SessionCompleted(message::ClusterSessionCompleted {
+			session: session_id.clone().into(),
+			sub_session: None,
+		}));
+
+		// completion, reported by other node, is ignored
+		ClusterCore::send_message(clusters[2].data.clone(), clusters[2].data.connection(&node1).unwrap(), session_completed());
+		loop_for(&mut core, time::Duration::from_millis(100));
+		assert!(clusters[1].client().encryption_session(&session_id).is_some());
+
+		// completion, reported by master, removes session
+		ClusterCore::send_message(clusters[0].data.clone(), clusters[0].data.connection(&node1).unwrap(), session_completed());
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].client().encryption_session(&session_id).is_none());
+	}
+
+	#[test]
+	fn cluster_threads_are_named_after_node() {
+		let config = make_clusters_configs(6270, 1, |_, _| ()).pop().unwrap();
+		let expected_prefix = format!("ss-{}-", config.self_key_pair.public()[0..4].iter().map(|b| format!("{:02x}", b)).collect::<String>());
+		assert_eq!(config.threads_name_prefix, expected_prefix);
+
+		let thread_name = make_pool(&config)
+			.spawn_fn(|| Ok::<_, ()>(::std::thread::current().name().map(|name| name.to_owned())))
+			.wait()
+			.unwrap()
+			.unwrap();
+		assert!(thread_name.starts_with(&expected_prefix));
+	}
+
+	#[test]
+	fn restored_connection_is_reported() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6271, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+		let restored_connections = |cluster: &Arc<ClusterCore>| {
+			let metrics = cluster.client().connection_metrics();
+			metrics.inbound.connections_restored + metrics.outbound.connections_restored
+		};
+		assert_eq!(restored_connections(&clusters[0]), 0);
+
+		// drop connection && reconnect
+		let node1 = clusters[1].client().self_node_id();
+		let is_inbound = clusters[0].connection(&node1).unwrap().is_inbound();
+		clusters[0].data.connections.remove(&node1, is_inbound);
+		assert!(clusters[0].data.connections.is_connection_lost(&node1));
+		for cluster in &clusters {
+			cluster.client().connect();
+		}
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		assert_eq!(restored_connections(&clusters[0]), 1);
+		assert!(!clusters[0].data.connections.is_connection_lost(&node1));
+	}
+
+	#[test]
+	fn run_keep_alive_now_disconnects_silent_node() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6273, 2, |i, config| if i == 0 {
+			config.disconnect_timeout = time::Duration::from_millis(50);
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// node1 sends nothing during the disconnect timeout
+		loop_for(&mut core, time::Duration::from_millis(100));
+		let node1 = clusters[1].client().self_node_id();
+		assert!(clusters[0].connection(&node1).is_some());
+		clusters[0].client().run_keep_alive_now();
+		assert!(clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn silent_node_is_disconnected_when_fake_clock_is_advanced() {
+		let mut core = Core::new().unwrap();
+		let clock = Arc::new(FakeClock::new());
+		let clusters = make_clusters_with_config(&core, 6275, 2, |i, config| if i == 0 {
+			config.clock = clock.clone() as Arc<Clock>;
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].client().self_node_id();
+		clusters[0].client().run_keep_alive_now();
+		assert!(clusters[0].connection(&node1).is_some());
+
+		clock.advance(time::Duration::from_secs(61));
+		clusters[0].client().run_keep_alive_now();
+		assert!(clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn connection_arbitration_keeps_connection_initiated_by_lower_node() {
+		let core = Core::new().unwrap();
+		let config = make_clusters_configs(6216, 2, |_, _| ()).remove(0);
+		let self_node = config.self_key_pair.public().clone();
+		let peer_node = config.nodes.keys().find(|n| **n != self_node).unwrap().clone();
+		let connections = ClusterConnections::new(&config).unwrap();
+
+		let listener = ::std::net::TcpListener::bind("127.0.0.1:6217").unwrap();
+		let listen_address = listener.local_addr().unwrap();
+		let make_connection = |is_inbound| {
+			let stream = ::std::net::TcpStream::connect(&listen_address).unwrap();
+			let stream = TcpStream::from_stream(stream, &core.handle()).unwrap();
+			Connection::from_parts(peer_node.clone(), listen_address, is_inbound, stream.into())
+		};
+
+		assert!(connections.insert(make_connection(true)));
+		assert_eq!(connections.insert(make_connection(false)), self_node < peer_node);
+		assert_eq!(connections.get(&peer_node).unwrap().is_inbound(), self_node > peer_node);
+	}
+
+	#[test]
+	fn connection_is_closed_when_message_is_not_sent_in_time() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6218, 2, |_, config| config.send_timeout = time::Duration::from_millis(200));
+
+		// peer accepts connection, but never reads from it
+		let listener = ::std::net::TcpListener::bind("127.0.0.1:6220").unwrap();
+		let peer_address = listener.local_addr().unwrap();
+		let stream = ::std::net::TcpStream::connect(&peer_address).unwrap();
+		let _peer_stream = listener.accept().unwrap();
+		let stream = TcpStream::from_stream(stream, &core.handle()).unwrap();
+
+		let node1 = clusters[1].client().self_node_id();
+		let connection = Connection::from_parts(node1.clone(), peer_address, false, stream.into());
+		assert!(clusters[0].data.connections.insert(connection.clone()));
+
+		// messages are sent one-by-one until socket buffers are full => last write never completes
+		for messages_sent in 0..512 {
+			if clusters[0].connection(&node1).is_none() {
+				break;
+			}
+
+			ClusterCore::send_message(clusters[0].data.clone(), connection.clone(), Message::Encryption(EncryptionMessage::SessionError(message::SessionError {
+				session: SessionId::default().into(),
+				error: ::std::iter::repeat('x').take(48 * 1024).collect(),
+				code: None,
+			})));
+			loop_until(&mut core, time::Duration::from_millis(3000), || clusters[0].connection(&node1).is_none()
+				|| connection.counters.messages_sent.load(Ordering::Relaxed) > messages_sent);
+		}
+		assert!(clusters[0].connection(&node1).is_none());
+	}
+
+	#[test]
+	fn cluster_view_refuses_to_send_to_non_session_node() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6221, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// node2 is connected, but it is not a part of the view
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let node2 = clusters[2].config().self_key_pair.public().clone();
+		let nodes: BTreeSet<_> = vec![clusters[0].config().self_key_pair.public().clone(), node1.clone()].into_iter().collect();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), nodes);
+		assert!(clusters[0].connection(&node2).is_some());
+		assert_eq!(cluster_view.send(&node2, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))), Err(Error::InvalidNodeForRequest));
+		assert_eq!(cluster_view.send(&node1, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))), Ok(()));
+	}
+
+	#[test]
+	fn active_encryption_session_is_in_session_report() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6277, 3);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let started_at = clusters[0].data.clock.now();
+		let _session = clusters[0].client().new_encryption_session(SessionId::default(), 1).unwrap();
+		let report = clusters[0].client().session_report();
+		assert_eq!(report.len(), 1);
+		assert_eq!(report[0].id, SessionId::default());
+		assert_eq!(report[0].sub_session_id, None);
+		assert_eq!(report[0].kind, SessionKind::Encryption);
+		assert!(report[0].creation_time >= started_at);
+		assert!(clusters[0].data.clock.now() - report[0].creation_time < time::Duration::from_secs(5));
+		assert_eq!(report[0].participants, 3);
+	}
+
+	#[test]
+	fn view_is_not_restricted_to_nodes_outside_of_it() {
+		let core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6300, 1);
+		let nodes: BTreeSet<_> = (0..3).map(|_| Random.generate().unwrap().public().clone()).collect();
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), nodes.clone());
+
+		let mut invalid_set: BTreeSet<_> = nodes.iter().take(1).cloned().collect();
+		invalid_set.insert(Random.generate().unwrap().public().clone());
+		assert_eq!(cluster_view.restrict_nodes(&invalid_set), Err(Error::InvalidNodeForRequest));
+		assert_eq!(cluster_view.nodes(), nodes);
+
+		let valid_set: BTreeSet<_> = nodes.iter().take(2).cloned().collect();
+		assert_eq!(cluster_view.restrict_nodes(&valid_set), Ok(()));
+		assert_eq!(cluster_view.nodes(), valid_set);
+	}
+
+	#[test]
+	fn send_to_set_is_delivered_to_given_nodes_only() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6280, 4);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let mut nodes = clusters[0].data.connections.connected_nodes();
+		nodes.insert(node0.clone());
+		let cluster_view = ClusterView::new(clusters[0].data.clone(), nodes);
+
+		// node outside of the view => nothing is sent
+		let mut invalid_set: BTreeSet<_> = clusters[1..3].iter().map(|c| c.config().self_key_pair.public().clone()).collect();
+		invalid_set.insert(Random.generate().unwrap().public().clone());
+		assert_eq!(cluster_view.send_to_set(&invalid_set, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))),
+			Err(Error::InvalidNodeForRequest));
+
+		// only nodes 1 && 2 receive the message
+		let confirmed_set: BTreeSet<_> = clusters[1..3].iter().map(|c| c.config().self_key_pair.public().clone()).collect();
+		assert_eq!(cluster_view.send_to_set(&confirmed_set, Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: 1 }))), Ok(()));
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1..3].iter()
+			.all(cluster| cluster.client().connection_stats()[&node0].messages_received == 1));
+		loop_for(&mut core, time::Duration::from_millis(100));
+		assert_eq!(clusters[1].client().connection_stats()[&node0].messages_received, 1);
+		assert_eq!(clusters[3].client().connection_stats()[&node0].messages_received, 0);
+	}
+
+	#[test]
+	fn messages_are_not_compressed_when_peer_does_not_support_compression() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6284, 2, |i, config| if i == 1 {
+			config.compression_threshold = None;
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// give nodes some time to exchange capabilities
+		loop_for(&mut core, time::Duration::from_millis(100));
+		for cluster in &clusters {
+			assert!(cluster.data.connections.active_connections().iter().all(|c| !c.is_compression_enabled()));
+		}
+	}
+
+	#[cfg(feature = "compression")]
+	#[test]
+	fn large_messages_are_compressed_when_both_nodes_support_compression() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6286, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter()
+			.all(|cluster| cluster.data.connections.active_connections().iter().all(|c| c.is_compression_enabled())));
+
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		let large_message = || Message::Encryption(EncryptionMessage::SessionError(message::SessionError {
+			session: SessionId::default().into(),
+			error: ::std::iter::repeat('x').take(32 * 1024).collect(),
+			code: None,
+		}));
+		let uncompressed_size = encrypt_message(&Random.generate().unwrap(), serialize_message(large_message()).unwrap()).unwrap().len();
+
+		let stats = clusters[1].client().connection_stats()[&node0].clone();
+		ClusterCore::send_message(clusters[0].data.clone(), clusters[0].connection(&node1).unwrap(), large_message());
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[1].client().connection_stats()[&node0].messages_received > stats.messages_received);
+
+		let received_bytes = clusters[1].client().connection_stats()[&node0].bytes_received - stats.bytes_received;
+		assert!(received_bytes < uncompressed_size / 10);
+		assert!(clusters[1].connection(&node0).is_some());
+	}
+
+	#[test]
+	fn connection_from_node_not_in_configuration_is_refused() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters_with_config(&core, 6228, 2, |i, config| {
+			config.initial_connect_delay = time::Duration::from_millis(0);
+			if i == 0 {
+				let self_node_id = config.self_key_pair.public().clone();
+				config.nodes = config.nodes.iter()
+					.filter(|&(node_id, _)| node_id == &self_node_id)
+					.map(|(node_id, node_address)| (node_id.clone(), node_address.clone()))
+					.collect();
+			}
+		});
+		run_clusters(&clusters);
+
+		// node1 knows about node0, but node0 doesn't know about node1 => handshake is rejected by node0
+		let node0 = clusters[0].config().self_key_pair.public().clone();
+		let node1 = clusters[1].config().self_key_pair.public().clone();
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters[0].client().connection_metrics().inbound.handshakes_rejected > 0);
+		assert!(clusters[0].connection(&node1).is_none());
+		assert!(clusters[1].connection(&node0).is_none());
+	}
+
+	#[test]
+	fn touched_connection_is_not_disconnected_by_keep_alive() {
+		let mut core = Core::new().unwrap();
+		let clock = Arc::new(FakeClock::new());
+		let clusters = make_clusters_with_config(&core, 6288, 2, |i, config| if i == 0 {
+			config.clock = clock.clone() as Arc<Clock>;
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		// disconnect timeout has elapsed since connection has been established, but not since it has been touched
+		let node1 = clusters[1].client().self_node_id();
+		clock.advance(time::Duration::from_secs(40));
+		assert_eq!(clusters[0].client().touch_connection(&node1), Ok(()));
+		clock.advance(time::Duration::from_secs(40));
+		clusters[0].client().run_keep_alive_now();
+		assert!(clusters[0].connection(&node1).is_some());
+
+		let unknown_node = Random.generate().unwrap().public().clone();
+		assert_eq!(clusters[0].client().touch_connection(&unknown_node), Err(Error::NodeDisconnected));
+	}
+
+	#[test]
+	fn stale_keep_alive_does_not_update_last_message_time() {
+		let mut core = Core::new().unwrap();
+		let clock = Arc::new(FakeClock::new());
+		let clusters = make_clusters_with_config(&core, 6233, 2, |i, config| if i == 0 {
+			config.clock = clock.clone() as Arc<Clock>;
+		});
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].client().self_node_id();
+		let connection = clusters[0].connection(&node1).unwrap();
+		let keep_alive = |nonce| Message::Cluster(ClusterMessage::KeepAlive(message::KeepAlive { nonce: nonce }));
+
+		clock.advance(time::Duration::from_secs(10));
+		ClusterCore::process_connection_message(clusters[0].data.clone(), connection.clone(), keep_alive(5));
+		let last_message_time = connection.last_message_time();
+		assert_eq!(last_message_time, clock.now());
+
+		// replayed && older keep alives are ignored
+		clock.advance(time::Duration::from_secs(10));
+		ClusterCore::process_connection_message(clusters[0].data.clone(), connection.clone(), keep_alive(5));
+		ClusterCore::process_connection_message(clusters[0].data.clone(), connection.clone(), keep_alive(4));
+		assert_eq!(connection.last_message_time(), last_message_time);
+		assert_eq!(clusters[0].client().connection_stats()[&node1].messages_rejected, 2);
+
+		ClusterCore::process_connection_message(clusters[0].data.clone(), connection.clone(), keep_alive(6));
+		assert_eq!(connection.last_message_time(), clock.now());
+
+		// keep alives of older nodes are not numbered
+		clock.advance(time::Duration::from_secs(10));
+		ClusterCore::process_connection_message(clusters[0].data.clone(), connection.clone(), keep_alive(0));
+		assert_eq!(connection.last_message_time(), clock.now());
+		assert_eq!(clusters[0].client().connection_stats()[&node1].messages_rejected, 2);
+	}
+
+	#[test]
+	fn connection_events_are_recorded_in_order() {
+		let mut core = Core::new().unwrap();
+		let clusters = make_clusters(&core, 6235, 2);
+		run_clusters(&clusters);
+		loop_until(&mut core, time::Duration::from_millis(300), || clusters.iter().all(all_connections_established));
+
+		let node1 = clusters[1].client().self_node_id();
+		let connection = clusters[0].connection(&node1).unwrap();
+		clusters[0].data.connections.remove(&node1, connection.is_inbound());
+		clusters[0].data.connections.blacklist(&node1, time::Duration::from_secs(60));
+
+		let events: Vec<_> = clusters[0].client().recent_connection_events(3).into_iter().map(|e| e.kind).collect();
+		assert_eq!(events, vec![
+			ConnectionEventKind::Connected(node1.clone()),
+			ConnectionEventKind::Disconnected(node1.clone()),
+			ConnectionEventKind::Blacklisted(node1.clone()),
+		]);
+
+		let events = clusters[0].client().recent_connection_events(100);
+		assert!(events.windows(2).all(|w| w[0].time <= w[1].time));
+		assert_eq!(clusters[0].client().recent_connection_events(1).into_iter().map(|e| e.kind).collect::<Vec<_>>(),
+			vec![ConnectionEventKind::Blacklisted(node1)]);
+	}
+
+	#[test]
+	fn oldest_connection_events_are_dropped() {
+		let configs = make_clusters_configs(6237, 2, |_, config| config.max_connection_events = 2);
+		let connections = ClusterConnections::new(&configs[0]).unwrap();
+		let node_address: SocketAddr = "127.0.0.1:6238".parse().unwrap();
+		let other_node_address: SocketAddr = "127.0.0.1:6239".parse().unwrap();
+		for _ in 0..3 {
+			connections.on_handshake_failed(node_address);
+		}
+		connections.on_handshake_failed(other_node_address);
+
+		let events: Vec<_> = connections.recent_events(10).into_iter().map(|e| e.kind).collect();
+		assert_eq!(events, vec![
+			ConnectionEventKind::HandshakeFailed(node_address),
+			ConnectionEventKind::HandshakeFailed(other_node_address),
+		]);
+	}
+
+	#[test]
+	fn write_retry_delay_is_capped() {
+		let interval = time::Duration::from_millis(50);
+		assert_eq!(write_retry_delay(interval, 0), time::Duration::from_millis(50));
+		assert_eq!(write_retry_delay(interval, 2), time::Duration::from_millis(200));
+		assert_eq!(write_retry_delay(interval, 10), time::Duration::from_millis(5000));
+		assert_eq!(write_retry_delay(interval, 32), time::Duration::from_millis(5000));
+		assert_eq!(write_retry_delay(interval, 100), time::Duration::from_millis(5000));
+	}
 }
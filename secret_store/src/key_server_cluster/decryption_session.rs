@@ -619,6 +619,16 @@ impl DecryptionSessionId {
 			access_key: sub_session_id,
 		}
 	}
+
+	/// Get id of encryption session.
+	pub fn session_id(&self) -> &SessionId {
+		&self.id
+	}
+
+	/// Get decryption session access key.
+	pub fn sub_session_id(&self) -> &Secret {
+		&self.access_key
+	}
 }
 
 impl PartialOrd for DecryptionSessionId {
@@ -702,7 +712,7 @@ mod tests {
 	use ethkey::{self, Random, Generator, Public, Secret};
 	use key_server_cluster::{NodeId, DocumentKeyShare, SessionId, Error, DocumentEncryptedKeyShadow};
 	use key_server_cluster::cluster::tests::DummyCluster;
-	use key_server_cluster::decryption_session::{SessionImpl, SessionParams, SessionState};
+	use key_server_cluster::decryption_session::{SessionImpl, SessionParams, SessionState, DecryptionSessionId};
 	use key_server_cluster::message::{self, Message, DecryptionMessage};
 	use key_server_cluster::math;
 
@@ -1152,4 +1162,14 @@ mod tests {
 	fn decryption_session_works_over_network() {
 		// TODO
 	}
+
+	#[test]
+	fn decryption_session_id_is_built_from_session_and_sub_session_ids() {
+		let session_id = SessionId::from([1u8; 32]);
+		let sub_session_id = Random.generate().unwrap().secret().clone();
+		let decryption_session_id = DecryptionSessionId::new(session_id.clone(), sub_session_id.clone());
+		assert_eq!(decryption_session_id.session_id(), &session_id);
+		assert_eq!(decryption_session_id.sub_session_id(), &sub_session_id);
+		assert_eq!(DecryptionSessionId::new(decryption_session_id.session_id().clone(), decryption_session_id.sub_session_id().clone()), decryption_session_id);
+	}
 }
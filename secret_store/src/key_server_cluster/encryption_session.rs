@@ -18,6 +18,7 @@ use std::collections::{BTreeSet, BTreeMap, VecDeque};
 use std::fmt::{Debug, Formatter, Error as FmtError};
 use std::time;
 use std::sync::Arc;
+use futures::{self, Complete, Oneshot};
 use parking_lot::{Condvar, Mutex};
 use ethkey::{Public, Secret};
 use key_server_cluster::{Error, NodeId, SessionId, KeyStorage, DocumentKeyShare};
@@ -58,6 +59,8 @@ pub struct SessionImpl {
 	cluster: Arc<Cluster>,
 	/// SessionImpl completion condvar.
 	completed: Condvar,
+	/// Listeners, waiting for session completion.
+	completion_listeners: Mutex<Vec<Complete<Result<Public, Error>>>>,
 	/// Mutable session data.
 	data: Mutex<SessionData>,
 }
@@ -188,6 +191,7 @@ impl SessionImpl {
 			key_storage: params.key_storage,
 			cluster: params.cluster,
 			completed: Condvar::new(),
+			completion_listeners: Mutex::new(Vec::new()),
 			data: Mutex::new(SessionData {
 				state: SessionState::WaitingForInitialization,
 				simulate_faulty_behaviour: false,
@@ -214,6 +218,16 @@ impl SessionImpl {
 		self.data.lock().derived_point.clone()
 	}
 
+	/// Get threshold of this session. Is known after session initialization is completed.
+	pub fn threshold(&self) -> Option<usize> {
+		self.data.lock().threshold.clone()
+	}
+
+	/// Get nodes, participating in this session. Is known after session initialization is completed.
+	pub fn nodes(&self) -> BTreeSet<NodeId> {
+		self.data.lock().nodes.keys().cloned().collect()
+	}
+
 	/// Simulate faulty encryption session behaviour.
 	pub fn simulate_faulty_behaviour(&self) {
 		self.data.lock().simulate_faulty_behaviour = true;
@@ -495,7 +509,7 @@ impl SessionImpl {
 
 		// we have received enough confirmations => complete session
 		data.state = SessionState::Finished;
-		self.completed.notify_all();
+		self.notify_completed(&data);
 
 		Ok(())
 	}
@@ -506,10 +520,12 @@ impl SessionImpl {
 
 		warn!("{}: encryption session failed with error: {} from {}", self.node(), message.error, sender);
 
+		let error = message.code.and_then(|code| code.to_error())
+			.unwrap_or_else(|| Error::Io(message.error.clone()));
 		data.state = SessionState::Failed;
-		data.joint_public = Some(Err(Error::Io(message.error.clone())));
-		data.secret_point = Some(Err(Error::Io(message.error.clone())));
-		self.completed.notify_all();
+		data.joint_public = Some(Err(error.clone()));
+		data.secret_point = Some(Err(error));
+		self.notify_completed(&data);
 
 		Ok(())
 	}
@@ -525,7 +541,7 @@ impl SessionImpl {
 		data.state = SessionState::Failed;
 		data.joint_public = Some(Err(Error::NodeDisconnected));
 		data.secret_point = Some(Err(Error::NodeDisconnected));
-		self.completed.notify_all();
+		self.notify_completed(&data);
 	}
 
 	/// When session timeout has occured.
@@ -537,7 +553,7 @@ impl SessionImpl {
 		data.state = SessionState::Failed;
 		data.joint_public = Some(Err(Error::NodeDisconnected));
 		data.secret_point = Some(Err(Error::NodeDisconnected));
-		self.completed.notify_all();
+		self.notify_completed(&data);
 	}
 
 	/// Complete initialization (when all other nodex has responded with confirmation)
@@ -704,6 +720,43 @@ impl SessionImpl {
 	}
 }
 
+impl SessionImpl {
+	/// Get future, which is resolved when session is either finished, or failed.
+	pub fn completion(&self) -> Oneshot<Result<Public, Error>> {
+		let data = self.data.lock();
+		let (complete, completion) = futures::oneshot();
+		match data.state {
+			SessionState::Finished | SessionState::Failed => { let _ = complete.send(SessionImpl::completion_result(&data)); },
+			_ => self.completion_listeners.lock().push(complete),
+		}
+		completion
+	}
+
+	/// Notify all waiters that session is completed.
+	fn notify_completed(&self, data: &SessionData) {
+		self.completed.notify_all();
+
+		let result = SessionImpl::completion_result(data);
+		for listener in self.completion_listeners.lock().drain(..) {
+			let _ = listener.send(result.clone());
+		}
+	}
+
+	/// Get result of completed session.
+	fn completion_result(data: &SessionData) -> Result<Public, Error> {
+		// secret point is only known to master node
+		data.secret_point.clone().unwrap_or(Err(Error::InvalidStateForRequest))
+	}
+
+	/// Get session error, if session has failed.
+	pub fn error(&self) -> Option<Error> {
+		match self.data.lock().joint_public {
+			Some(Err(ref err)) => Some(err.clone()),
+			_ => None,
+		}
+	}
+}
+
 impl Session for SessionImpl {
 	#[cfg(test)]
 	fn joint_public_key(&self) -> Option<Result<Public, Error>> {
@@ -802,6 +855,7 @@ mod tests {
 	use std::time;
 	use std::sync::Arc;
 	use std::collections::{BTreeSet, BTreeMap, VecDeque};
+	use serde_json;
 	use tokio_core::reactor::Core;
 	use ethkey::{Random, Generator};
 	use key_server_cluster::{NodeId, SessionId, Error, DummyKeyStorage};
@@ -1206,6 +1260,35 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn session_error_code_is_passed_to_other_node() {
+		let (sid, m, _, l) = make_simple_cluster(0, 2).unwrap();
+		let error = message::SessionError {
+			session: sid.into(),
+			error: format!("{:?}", Error::InvalidThreshold),
+			code: Some((&Error::InvalidThreshold).into()),
+		};
+
+		// error code survives serialization
+		let error: message::SessionError = serde_json::from_slice(&serde_json::to_vec(&error).unwrap()).unwrap();
+		assert_eq!(error.code, Some(message::SessionErrorCode::InvalidThreshold));
+		assert!(!error.code.unwrap().is_transient());
+
+		l.first_slave().on_session_error(m, &error).unwrap();
+		assert_eq!(l.first_slave().joint_public_key(), Some(Err(Error::InvalidThreshold)));
+	}
+
+	#[test]
+	fn session_error_without_code_is_accepted() {
+		let (sid, m, _, l) = make_simple_cluster(0, 2).unwrap();
+		let error: message::SessionError = serde_json::from_str(&format!(r#"{{"session":{},"error":"some error"}}"#,
+			serde_json::to_string(&message::MessageSessionId::from(sid)).unwrap())).unwrap();
+		assert_eq!(error.code, None);
+
+		l.first_slave().on_session_error(m, &error).unwrap();
+		assert_eq!(l.first_slave().joint_public_key(), Some(Err(Error::Io("some error".into()))));
+	}
+
 	#[test]
 	fn encryption_session_works_over_network() {
 		//::util::log::init_log();
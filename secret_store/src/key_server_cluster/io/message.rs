@@ -15,9 +15,17 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::io::Cursor;
+#[cfg(feature = "compression")]
+use std::io::{Read, Write};
 use std::u16;
 use std::ops::Deref;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "compression")]
+use flate2::Compression;
+#[cfg(feature = "compression")]
+use flate2::read::DeflateDecoder;
+#[cfg(feature = "compression")]
+use flate2::write::DeflateEncoder;
 use serde_json;
 use ethcrypto::ecdh::agree;
 use ethcrypto::ecies::{encrypt_single_message, decrypt_single_message};
@@ -29,6 +37,10 @@ use key_server_cluster::message::{Message, ClusterMessage, EncryptionMessage, De
 
 /// Size of serialized header.
 pub const MESSAGE_HEADER_SIZE: usize = 4;
+/// Version of message with plain payload.
+const MESSAGE_VERSION: u8 = 1;
+/// Version of message with deflate-compressed payload.
+pub const COMPRESSED_MESSAGE_VERSION: u8 = 2;
 
 #[derive(Debug, PartialEq)]
 /// Message header.
@@ -66,6 +78,10 @@ pub fn serialize_message(message: Message) -> Result<SerializedMessage, Error> {
 		Message::Cluster(ClusterMessage::NodePrivateKeySignature(payload))					=> (2, serde_json::to_vec(&payload)),
 		Message::Cluster(ClusterMessage::KeepAlive(payload))								=> (3, serde_json::to_vec(&payload)),
 		Message::Cluster(ClusterMessage::KeepAliveResponse(payload))						=> (4, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::Disconnect(payload))								=> (5, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::Ping(payload))										=> (6, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::Pong(payload))										=> (7, serde_json::to_vec(&payload)),
+		Message::Cluster(ClusterMessage::SessionCompleted(payload))							=> (8, serde_json::to_vec(&payload)),
 
 		Message::Encryption(EncryptionMessage::InitializeSession(payload))					=> (50, serde_json::to_vec(&payload)),
 		Message::Encryption(EncryptionMessage::ConfirmInitialization(payload))				=> (51, serde_json::to_vec(&payload)),
@@ -86,7 +102,7 @@ pub fn serialize_message(message: Message) -> Result<SerializedMessage, Error> {
 	let payload = payload.map_err(|err| Error::Serde(err.to_string()))?;
 	build_serialized_message(MessageHeader {
 		kind: message_kind,
-		version: 1,
+		version: MESSAGE_VERSION,
 		size: 0,
 	}, payload)
 }
@@ -98,6 +114,10 @@ pub fn deserialize_message(header: &MessageHeader, payload: Vec<u8>) -> Result<M
 		2	=> Message::Cluster(ClusterMessage::NodePrivateKeySignature(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		3	=> Message::Cluster(ClusterMessage::KeepAlive(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		4	=> Message::Cluster(ClusterMessage::KeepAliveResponse(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		5	=> Message::Cluster(ClusterMessage::Disconnect(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		6	=> Message::Cluster(ClusterMessage::Ping(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		7	=> Message::Cluster(ClusterMessage::Pong(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
+		8	=> Message::Cluster(ClusterMessage::SessionCompleted(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 
 		50	=> Message::Encryption(EncryptionMessage::InitializeSession(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
 		51	=> Message::Encryption(EncryptionMessage::ConfirmInitialization(serde_json::from_slice(&payload).map_err(|err| Error::Serde(err.to_string()))?)),
@@ -118,6 +138,27 @@ pub fn deserialize_message(header: &MessageHeader, payload: Vec<u8>) -> Result<M
 	})
 }
 
+/// Decode plain (not encrypted) message from given bytes (header && payload).
+/// Returns error on any invalid input, including truncated messages.
+pub fn decode_message(data: &[u8]) -> Result<Message, Error> {
+	if data.len() < MESSAGE_HEADER_SIZE {
+		return Err(Error::InvalidMessage);
+	}
+
+	let header = deserialize_header(&data[..MESSAGE_HEADER_SIZE])?;
+	if header.size as usize != data.len() - MESSAGE_HEADER_SIZE {
+		return Err(Error::InvalidMessage);
+	}
+
+	decode_payload(&header, data[MESSAGE_HEADER_SIZE..].to_vec())
+}
+
+/// Decode plain (not encrypted) message payload.
+pub fn decode_payload(header: &MessageHeader, payload: Vec<u8>) -> Result<Message, Error> {
+	decompress_payload(header, payload)
+		.and_then(|payload| deserialize_message(header, payload))
+}
+
 /// Encrypt serialized message.
 pub fn encrypt_message(key: &KeyPair, message: SerializedMessage) -> Result<SerializedMessage, Error> {
 	let mut header: Vec<_> = message.into();
@@ -128,6 +169,65 @@ pub fn encrypt_message(key: &KeyPair, message: SerializedMessage) -> Result<Seri
 	build_serialized_message(header, encrypted_payload)
 }
 
+/// Compress payload of serialized message, if it is not smaller than given threshold.
+/// Message is left as is, if compression is not supported or it doesn't make payload smaller.
+pub fn compress_message(message: SerializedMessage, threshold: usize) -> Result<SerializedMessage, Error> {
+	if message.len() < MESSAGE_HEADER_SIZE + threshold {
+		return Ok(message);
+	}
+
+	let mut header: Vec<_> = message.into();
+	let payload = header.split_off(MESSAGE_HEADER_SIZE);
+	let mut header = deserialize_header(&header)?;
+	match compress_payload(&payload)? {
+		Some(compressed_payload) if compressed_payload.len() < payload.len() => {
+			header.version = COMPRESSED_MESSAGE_VERSION;
+			build_serialized_message(header, compressed_payload)
+		},
+		_ => build_serialized_message(header, payload),
+	}
+}
+
+/// Decompress message payload, if it has been compressed by sender.
+pub fn decompress_payload(header: &MessageHeader, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+	if header.version != COMPRESSED_MESSAGE_VERSION {
+		return Ok(payload);
+	}
+
+	decompress(&payload)
+}
+
+#[cfg(feature = "compression")]
+fn compress_payload(payload: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+	let mut encoder = DeflateEncoder::new(Vec::new(), Compression::Default);
+	encoder.write_all(payload)?;
+	Ok(Some(encoder.finish()?))
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress_payload(_payload: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+	Ok(None)
+}
+
+#[cfg(feature = "compression")]
+fn decompress(payload: &[u8]) -> Result<Vec<u8>, Error> {
+	// uncompressed payload has passed the same size check as plain payloads => anything larger is invalid
+	let mut decompressed = Vec::new();
+	DeflateDecoder::new(payload).take(u16::MAX as u64 + 1).read_to_end(&mut decompressed)
+		.map_err(|_| Error::InvalidMessage)?;
+	if decompressed.len() > u16::MAX as usize {
+		return Err(Error::InvalidMessage);
+	}
+
+	Ok(decompressed)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_payload: &[u8]) -> Result<Vec<u8>, Error> {
+	// compression is never announced to peers => they must not send compressed messages
+	Err(Error::InvalidMessage)
+}
+
 /// Decrypt serialized message.
 pub fn decrypt_message(key: &KeyPair, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
 	Ok(decrypt_single_message(key.secret(), &payload)?)
@@ -183,9 +283,10 @@ pub mod tests {
 	use futures::Poll;
 	use tokio_io::{AsyncRead, AsyncWrite};
 	use ethkey::{KeyPair, Public};
-	use key_server_cluster::message::Message;
-	use super::{MESSAGE_HEADER_SIZE, MessageHeader, compute_shared_key, encrypt_message, serialize_message,
-		serialize_header, deserialize_header};
+	use key_server_cluster::SessionId;
+	use key_server_cluster::message::{self, Message, EncryptionMessage};
+	use super::{MESSAGE_HEADER_SIZE, COMPRESSED_MESSAGE_VERSION, MessageHeader, compute_shared_key, encrypt_message, serialize_message,
+		serialize_header, deserialize_header, compress_message, decompress_payload, decode_message};
 
 	pub struct TestIo {
 		self_key_pair: KeyPair,
@@ -270,4 +371,98 @@ pub mod tests {
 		let deserialized_header = deserialize_header(&serialized_header).unwrap();
 		assert_eq!(deserialized_header, header);
 	}
+
+	fn session_error_message() -> Message {
+		Message::Encryption(EncryptionMessage::SessionError(message::SessionError {
+			session: SessionId::default().into(),
+			error: "session error".into(),
+			code: None,
+		}))
+	}
+
+	fn large_message() -> Message {
+		Message::Encryption(EncryptionMessage::SessionError(message::SessionError {
+			session: SessionId::default().into(),
+			error: ::std::iter::repeat('x').take(16 * 1024).collect(),
+			code: None,
+		}))
+	}
+
+	#[test]
+	fn valid_message_is_decoded() {
+		let serialized_message = serialize_message(session_error_message()).unwrap();
+		match decode_message(&serialized_message) {
+			Ok(Message::Encryption(EncryptionMessage::SessionError(ref message))) => assert_eq!(message.error, "session error"),
+			_ => panic!("expected SessionError message"),
+		}
+	}
+
+	#[test]
+	fn truncated_message_is_not_decoded() {
+		let serialized_message = serialize_message(session_error_message()).unwrap();
+		for len in 0..serialized_message.len() {
+			assert!(decode_message(&serialized_message[..len]).is_err());
+		}
+	}
+
+	#[test]
+	fn garbage_is_not_decoded() {
+		fn make_message(kind: u8, payload: &[u8]) -> Vec<u8> {
+			let mut message = serialize_header(&MessageHeader {
+				version: 1,
+				kind: kind,
+				size: payload.len() as u16,
+			}).unwrap();
+			message.extend_from_slice(payload);
+			message
+		}
+
+		// malformed payloads are never decoded
+		let malformed: Vec<&[u8]> = vec![&b""[..], &b"{"[..], &[0xff; 64][..]];
+		// well-formed payloads could be valid for some kinds (i.e. empty messages), but must never cause panic
+		let well_formed: Vec<&[u8]> = vec![&b"null"[..], &b"[]"[..], &b"{}"[..],
+			// multibyte character where hex prefix is expected
+			&b"{\"session\":\"\xe2\x82\xac0\"}"[..],
+			&b"{\"session\":\"0x00\",\"error\":1}"[..]];
+		for kind in 0..256 {
+			for payload in &malformed {
+				assert!(decode_message(&make_message(kind as u8, payload)).is_err());
+			}
+			for payload in &well_formed {
+				let _ = decode_message(&make_message(kind as u8, payload));
+			}
+		}
+
+		// invalid session id is not decoded
+		assert!(decode_message(&make_message(55, &b"{\"session\":\"\xe2\x82\xac0\",\"error\":\"\"}"[..])).is_err());
+	}
+
+	#[test]
+	fn message_smaller_than_threshold_is_not_compressed() {
+		let serialized_message = serialize_message(large_message()).unwrap();
+		let threshold = serialized_message.len();
+		assert_eq!(compress_message(serialized_message.clone(), threshold).unwrap(), serialized_message);
+	}
+
+	#[cfg(feature = "compression")]
+	#[test]
+	fn compressed_message_is_smaller_and_decompressed_back() {
+		let serialized_message = serialize_message(large_message()).unwrap();
+		let compressed_message = compress_message(serialized_message.clone(), 1024).unwrap();
+		assert!(compressed_message.len() < serialized_message.len() / 10);
+
+		let header = deserialize_header(&compressed_message[..MESSAGE_HEADER_SIZE]).unwrap();
+		assert_eq!(header.version, COMPRESSED_MESSAGE_VERSION);
+		assert_eq!(header.size as usize, compressed_message.len() - MESSAGE_HEADER_SIZE);
+		let payload = decompress_payload(&header, compressed_message[MESSAGE_HEADER_SIZE..].to_vec()).unwrap();
+		assert_eq!(&payload[..], &serialized_message[MESSAGE_HEADER_SIZE..]);
+	}
+
+	#[test]
+	fn invalid_compressed_message_is_not_decoded() {
+		// compressed payload, which is not a valid deflate stream
+		let mut message = serialize_header(&MessageHeader { version: COMPRESSED_MESSAGE_VERSION, kind: 55, size: 4 }).unwrap();
+		message.extend_from_slice(&[0xff; 4]);
+		assert!(decode_message(&message).is_err());
+	}
 }
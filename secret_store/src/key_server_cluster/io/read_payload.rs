@@ -21,7 +21,7 @@ use tokio_io::io::{read_exact, ReadExact};
 use ethkey::KeyPair;
 use key_server_cluster::Error;
 use key_server_cluster::message::Message;
-use key_server_cluster::io::message::{MessageHeader, deserialize_message, decrypt_message};
+use key_server_cluster::io::message::{MessageHeader, decode_payload, decrypt_message};
 
 /// Create future for read single message payload from the stream.
 pub fn read_payload<A>(a: A, header: MessageHeader) -> ReadPayload<A> where A: AsyncRead {
@@ -56,9 +56,9 @@ impl<A> Future for ReadPayload<A> where A: AsyncRead {
 		let (read, data) = try_ready!(self.reader.poll());
 		let payload = if let Some(key) = self.key.take() {
 			decrypt_message(&key, data)
-				.and_then(|data| deserialize_message(&self.header, data))
+				.and_then(|data| decode_payload(&self.header, data))
 		} else {
-			deserialize_message(&self.header, data)
+			decode_payload(&self.header, data)
 		};
 		Ok((read, payload).into())
 	}
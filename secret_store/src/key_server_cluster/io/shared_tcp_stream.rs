@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::Arc;
+use std::ops::Deref;
 use std::net::Shutdown;
 use std::io::{Read, Write, Error};
 use futures::Poll;
@@ -40,6 +41,14 @@ impl From<TcpStream> for SharedTcpStream {
 	}
 }
 
+impl Deref for SharedTcpStream {
+	type Target = TcpStream;
+
+	fn deref(&self) -> &TcpStream {
+		&self.io
+	}
+}
+
 impl AsyncRead for SharedTcpStream {}
 
 impl AsyncWrite for SharedTcpStream {
@@ -14,47 +14,74 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::io;
-use futures::{Future, Poll};
+use std::{io, mem};
+use std::io::Write;
+use futures::{Future, Poll, Async};
 use tokio_io::AsyncWrite;
-use tokio_io::io::{WriteAll, write_all};
 use ethkey::KeyPair;
 use key_server_cluster::message::Message;
-use key_server_cluster::io::{serialize_message, encrypt_message};
+use key_server_cluster::io::{serialize_message, encrypt_message, compress_message};
 
 /// Write plain message to the channel.
 pub fn write_message<A>(a: A, message: Message) -> WriteMessage<A> where A: AsyncWrite {
-	let (error, future) = match serialize_message(message)
+	let (error, buffer) = match serialize_message(message)
 		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())) {
-		Ok(message) => (None, write_all(a, message.into())),
-		Err(error) => (Some(error), write_all(a, Vec::new())),
+		Ok(message) => (None, message.into()),
+		Err(error) => (Some(error), Vec::new()),
 	};
-	WriteMessage {
-		error: error,
-		future: future,
-	}
+	WriteMessage::new(a, error, buffer)
 }
 
 /// Write encrypted message to the channel.
 pub fn write_encrypted_message<A>(a: A, key: &KeyPair, message: Message) -> WriteMessage<A> where A: AsyncWrite {
-	let (error, future) = match serialize_message(message)
+	let (error, buffer) = match serialize_message(message)
 		.and_then(|message| encrypt_message(key, message))
 		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())) {
-		Ok(message) => (None, write_all(a, message.into())),
-		Err(error) => (Some(error), write_all(a, Vec::new())),
+		Ok(message) => (None, message.into()),
+		Err(error) => (Some(error), Vec::new()),
 	};
+	WriteMessage::new(a, error, buffer)
+}
 
-
-	WriteMessage {
-		error: error,
-		future: future,
-	}
+/// Write encrypted message to the channel, compressing payloads, which are not smaller than given threshold.
+pub fn write_compressed_encrypted_message<A>(a: A, key: &KeyPair, message: Message, compression_threshold: usize) -> WriteMessage<A> where A: AsyncWrite {
+	let (error, buffer) = match serialize_message(message)
+		.and_then(|message| compress_message(message, compression_threshold))
+		.and_then(|message| encrypt_message(key, message))
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string())) {
+		Ok(message) => (None, message.into()),
+		Err(error) => (Some(error), Vec::new()),
+	};
+	WriteMessage::new(a, error, buffer)
 }
 
 /// Future message write.
 pub struct WriteMessage<A> {
 	error: Option<io::Error>,
-	future: WriteAll<A, Vec<u8>>,
+	stream: Option<A>,
+	buffer: Vec<u8>,
+	written: usize,
+}
+
+impl<A> WriteMessage<A> {
+	fn new(a: A, error: Option<io::Error>, buffer: Vec<u8>) -> Self {
+		WriteMessage {
+			error: error,
+			stream: Some(a),
+			buffer: buffer,
+			written: 0,
+		}
+	}
+}
+
+/// Is it safe to write the same message again after this error.
+/// Interrupted writes are retried by WriteMessage itself, so normally this error never leaves it.
+/// Timed out connection is considered broken.
+pub fn is_transient_write_error(error: &io::Error) -> bool {
+	match error.kind() {
+		io::ErrorKind::Interrupted => true,
+		_ => false,
+	}
 }
 
 impl<A> Future for WriteMessage<A> where A: AsyncWrite {
@@ -66,6 +93,106 @@ impl<A> Future for WriteMessage<A> where A: AsyncWrite {
 			return Err(err);
 		}
 
-		self.future.poll()
+		while self.written < self.buffer.len() {
+			let written = {
+				let stream = self.stream.as_mut().expect("poll is not called after completion; qed");
+				stream.write(&self.buffer[self.written..])
+			};
+			match written {
+				Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "zero-length write")),
+				Ok(written) => self.written += written,
+				Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Async::NotReady),
+				// nothing has been written => continue writing the rest of the message
+				Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+				Err(err) => return Err(err),
+			}
+		}
+
+		let stream = self.stream.take().expect("poll is not called after completion; qed");
+		Ok((stream, mem::replace(&mut self.buffer, Vec::new())).into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io;
+	use futures::{Future, Poll};
+	use tokio_io::{AsyncRead, AsyncWrite};
+	use key_server_cluster::message::{Message, ClusterMessage, KeepAlive};
+	use super::{write_message, is_transient_write_error};
+
+	/// Writer, which fails with given errors before writing given number of bytes.
+	struct FailingIo {
+		errors: Vec<(usize, io::ErrorKind)>,
+		written: Vec<u8>,
+	}
+
+	impl AsyncRead for FailingIo {}
+
+	impl AsyncWrite for FailingIo {
+		fn shutdown(&mut self) -> Poll<(), io::Error> {
+			Ok(().into())
+		}
+	}
+
+	impl io::Read for FailingIo {
+		fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+			Ok(0)
+		}
+	}
+
+	impl io::Write for FailingIo {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			let next_error = self.errors.first().cloned();
+			if let Some((fail_at, kind)) = next_error {
+				if self.written.len() == fail_at {
+					self.errors.remove(0);
+					return Err(io::Error::new(kind, "simulated error"));
+				}
+				// write up to the failure point
+				let len = ::std::cmp::min(buf.len(), fail_at - self.written.len());
+				self.written.extend_from_slice(&buf[..len]);
+				return Ok(len);
+			}
+
+			self.written.extend_from_slice(buf);
+			Ok(buf.len())
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn keep_alive() -> Message {
+		Message::Cluster(ClusterMessage::KeepAlive(KeepAlive { nonce: 1 }))
+	}
+
+	#[test]
+	fn interrupted_write_is_retried_before_message_is_written() {
+		let mut io = FailingIo { errors: vec![(0, io::ErrorKind::Interrupted)], written: Vec::new() };
+		let message = {
+			let (_, message) = write_message(&mut io, keep_alive()).wait().unwrap();
+			message
+		};
+		assert_eq!(io.written, message);
+	}
+
+	#[test]
+	fn interrupted_write_is_retried_after_partial_write() {
+		let mut io = FailingIo { errors: vec![(4, io::ErrorKind::Interrupted), (4, io::ErrorKind::Interrupted)], written: Vec::new() };
+		let message = {
+			let (_, message) = write_message(&mut io, keep_alive()).wait().unwrap();
+			message
+		};
+		assert_eq!(io.written, message);
+	}
+
+	#[test]
+	fn timed_out_write_is_not_transient() {
+		let io = FailingIo { errors: vec![(0, io::ErrorKind::TimedOut)], written: Vec::new() };
+		let err = write_message(io, keep_alive()).wait().err().unwrap();
+		assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+		assert!(!is_transient_write_error(&err));
 	}
 }
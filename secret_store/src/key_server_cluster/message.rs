@@ -17,7 +17,7 @@
 use std::fmt;
 use std::collections::{BTreeSet, BTreeMap};
 use ethkey::Secret;
-use key_server_cluster::SessionId;
+use key_server_cluster::{Error, SessionId};
 use super::{SerializableH256, SerializablePublic, SerializableSecret, SerializableSignature};
 
 pub type MessageSessionId = SerializableH256;
@@ -45,6 +45,16 @@ pub enum ClusterMessage {
 	KeepAlive(KeepAlive),
 	/// Keep alive message response.
 	KeepAliveResponse(KeepAliveResponse),
+	/// Connection is about to be closed by sender.
+	Disconnect(Disconnect),
+	/// Request for round-trip time measurement.
+	Ping(Ping),
+	/// Response to Ping message.
+	Pong(Pong),
+	/// Session has been completed on master node.
+	SessionCompleted(ClusterSessionCompleted),
+	/// Announce optional protocol features, supported by sender.
+	Capabilities(Capabilities),
 }
 
 #[derive(Clone, Debug)]
@@ -103,6 +113,10 @@ pub struct NodePrivateKeySignature {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 /// Ask if the node is still alive.
 pub struct KeepAlive {
+	/// Sequence number of message within connection. Every next KeepAlive has larger nonce.
+	/// Zero if sent by older node, which does not number its KeepAlive messages.
+	#[serde(default)]
+	pub nonce: u64,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -110,6 +124,41 @@ pub struct KeepAlive {
 pub struct KeepAliveResponse {
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Notify the node that connection is about to be closed.
+pub struct Disconnect {
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Ask the node to respond with Pong message.
+pub struct Ping {
+	/// Random value, which must be returned in response.
+	pub nonce: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Respond to Ping message.
+pub struct Pong {
+	/// Nonce of the Ping message.
+	pub nonce: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Notify session participants that session has been completed on master node.
+pub struct ClusterSessionCompleted {
+	/// Encryption session Id.
+	pub session: MessageSessionId,
+	/// Decryption session Id. None for encryption sessions.
+	pub sub_session: Option<SerializableSecret>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Announce optional protocol features, supported by sender.
+pub struct Capabilities {
+	/// Sender is able to decompress message payloads.
+	pub compression: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 /// Initialize new DKG session.
 pub struct InitializeSession {
@@ -174,6 +223,28 @@ pub struct SessionError {
 	pub session: MessageSessionId,
 	/// Public key share.
 	pub error: String,
+	/// Machine-readable error code. Could be missing if sent by older node.
+	#[serde(default)]
+	pub code: Option<SessionErrorCode>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+/// Machine-readable code of session error.
+pub enum SessionErrorCode {
+	InvalidNodeId,
+	DuplicateSessionId,
+	InvalidSessionId,
+	InvalidNodesCount,
+	InvalidNodesConfiguration,
+	InvalidThreshold,
+	InvalidStateForRequest,
+	InvalidMessage,
+	NodeDisconnected,
+	AccessDenied,
+	TooManySessions,
+	NotAcceptingSessions,
+	/// Error, which has no dedicated code. Human-readable description is the only source of details.
+	Other,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -256,6 +327,36 @@ pub struct DecryptionSessionCompleted {
 	pub sub_session: SerializableSecret,
 }
 
+impl Message {
+	/// Get name of message type. Unlike Display, never includes message contents.
+	pub fn kind(&self) -> &'static str {
+		match *self {
+			Message::Cluster(ClusterMessage::NodePublicKey(_)) => "Cluster::NodePublicKey",
+			Message::Cluster(ClusterMessage::NodePrivateKeySignature(_)) => "Cluster::NodePrivateKeySignature",
+			Message::Cluster(ClusterMessage::KeepAlive(_)) => "Cluster::KeepAlive",
+			Message::Cluster(ClusterMessage::KeepAliveResponse(_)) => "Cluster::KeepAliveResponse",
+			Message::Cluster(ClusterMessage::Disconnect(_)) => "Cluster::Disconnect",
+			Message::Cluster(ClusterMessage::Ping(_)) => "Cluster::Ping",
+			Message::Cluster(ClusterMessage::Pong(_)) => "Cluster::Pong",
+			Message::Cluster(ClusterMessage::SessionCompleted(_)) => "Cluster::SessionCompleted",
+			Message::Cluster(ClusterMessage::Capabilities(_)) => "Cluster::Capabilities",
+			Message::Encryption(EncryptionMessage::InitializeSession(_)) => "Encryption::InitializeSession",
+			Message::Encryption(EncryptionMessage::ConfirmInitialization(_)) => "Encryption::ConfirmInitialization",
+			Message::Encryption(EncryptionMessage::CompleteInitialization(_)) => "Encryption::CompleteInitialization",
+			Message::Encryption(EncryptionMessage::KeysDissemination(_)) => "Encryption::KeysDissemination",
+			Message::Encryption(EncryptionMessage::PublicKeyShare(_)) => "Encryption::PublicKeyShare",
+			Message::Encryption(EncryptionMessage::SessionError(_)) => "Encryption::SessionError",
+			Message::Encryption(EncryptionMessage::SessionCompleted(_)) => "Encryption::SessionCompleted",
+			Message::Decryption(DecryptionMessage::InitializeDecryptionSession(_)) => "Decryption::InitializeDecryptionSession",
+			Message::Decryption(DecryptionMessage::ConfirmDecryptionInitialization(_)) => "Decryption::ConfirmDecryptionInitialization",
+			Message::Decryption(DecryptionMessage::RequestPartialDecryption(_)) => "Decryption::RequestPartialDecryption",
+			Message::Decryption(DecryptionMessage::PartialDecryption(_)) => "Decryption::PartialDecryption",
+			Message::Decryption(DecryptionMessage::DecryptionSessionError(_)) => "Decryption::DecryptionSessionError",
+			Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(_)) => "Decryption::DecryptionSessionCompleted",
+		}
+	}
+}
+
 impl EncryptionMessage {
 	pub fn session_id(&self) -> &SessionId {
 		match *self {
@@ -294,6 +395,55 @@ impl DecryptionMessage {
 	}
 }
 
+impl SessionErrorCode {
+	/// Is this error caused by temporary condition, so that session could be retried later.
+	pub fn is_transient(&self) -> bool {
+		match *self {
+			SessionErrorCode::NodeDisconnected | SessionErrorCode::TooManySessions | SessionErrorCode::NotAcceptingSessions => true,
+			_ => false,
+		}
+	}
+
+	/// Convert back to error. Returns None if there's no dedicated error for this code.
+	pub fn to_error(&self) -> Option<Error> {
+		Some(match *self {
+			SessionErrorCode::InvalidNodeId => Error::InvalidNodeId,
+			SessionErrorCode::DuplicateSessionId => Error::DuplicateSessionId,
+			SessionErrorCode::InvalidSessionId => Error::InvalidSessionId,
+			SessionErrorCode::InvalidNodesCount => Error::InvalidNodesCount,
+			SessionErrorCode::InvalidNodesConfiguration => Error::InvalidNodesConfiguration,
+			SessionErrorCode::InvalidThreshold => Error::InvalidThreshold,
+			SessionErrorCode::InvalidStateForRequest => Error::InvalidStateForRequest,
+			SessionErrorCode::InvalidMessage => Error::InvalidMessage,
+			SessionErrorCode::NodeDisconnected => Error::NodeDisconnected,
+			SessionErrorCode::AccessDenied => Error::AccessDenied,
+			SessionErrorCode::TooManySessions => Error::TooManySessions,
+			SessionErrorCode::NotAcceptingSessions => Error::NotAcceptingSessions,
+			SessionErrorCode::Other => return None,
+		})
+	}
+}
+
+impl<'a> From<&'a Error> for SessionErrorCode {
+	fn from(err: &'a Error) -> Self {
+		match *err {
+			Error::InvalidNodeId => SessionErrorCode::InvalidNodeId,
+			Error::DuplicateSessionId => SessionErrorCode::DuplicateSessionId,
+			Error::InvalidSessionId => SessionErrorCode::InvalidSessionId,
+			Error::InvalidNodesCount => SessionErrorCode::InvalidNodesCount,
+			Error::InvalidNodesConfiguration => SessionErrorCode::InvalidNodesConfiguration,
+			Error::InvalidThreshold => SessionErrorCode::InvalidThreshold,
+			Error::InvalidStateForRequest => SessionErrorCode::InvalidStateForRequest,
+			Error::InvalidMessage => SessionErrorCode::InvalidMessage,
+			Error::NodeDisconnected => SessionErrorCode::NodeDisconnected,
+			Error::AccessDenied => SessionErrorCode::AccessDenied,
+			Error::TooManySessions => SessionErrorCode::TooManySessions,
+			Error::NotAcceptingSessions => SessionErrorCode::NotAcceptingSessions,
+			_ => SessionErrorCode::Other,
+		}
+	}
+}
+
 impl fmt::Display for Message {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match *self {
@@ -311,6 +461,11 @@ impl fmt::Display for ClusterMessage {
 			ClusterMessage::NodePrivateKeySignature(_) => write!(f, "NodePrivateKeySignature"),
 			ClusterMessage::KeepAlive(_) => write!(f, "KeepAlive"),
 			ClusterMessage::KeepAliveResponse(_) => write!(f, "KeepAliveResponse"),
+			ClusterMessage::Disconnect(_) => write!(f, "Disconnect"),
+			ClusterMessage::Ping(_) => write!(f, "Ping"),
+			ClusterMessage::Pong(_) => write!(f, "Pong"),
+			ClusterMessage::SessionCompleted(_) => write!(f, "SessionCompleted"),
+			ClusterMessage::Capabilities(_) => write!(f, "Capabilities"),
 		}
 	}
 }
@@ -341,3 +496,33 @@ impl fmt::Display for DecryptionMessage {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use ethkey::{Random, Generator};
+	use key_server_cluster::SessionId;
+	use super::{Message, ClusterMessage, EncryptionMessage, DecryptionMessage, Ping, KeepAlive, SessionError, DecryptionSessionCompleted};
+
+	#[test]
+	fn message_kind_does_not_include_contents() {
+		assert_eq!(Message::Cluster(ClusterMessage::Ping(Ping {
+			nonce: 42,
+		})).kind(), "Cluster::Ping");
+		assert_eq!(Message::Encryption(EncryptionMessage::SessionError(SessionError {
+			session: SessionId::default().into(),
+			error: "secret error details".into(),
+			code: None,
+		})).kind(), "Encryption::SessionError");
+		assert_eq!(Message::Decryption(DecryptionMessage::DecryptionSessionCompleted(DecryptionSessionCompleted {
+			session: SessionId::default().into(),
+			sub_session: Random.generate().unwrap().secret().clone().into(),
+		})).kind(), "Decryption::DecryptionSessionCompleted");
+	}
+
+	#[test]
+	fn keep_alive_from_older_node_is_deserialized() {
+		let keep_alive: KeepAlive = serde_json::from_str("{}").unwrap();
+		assert_eq!(keep_alive.nonce, 0);
+	}
+}
@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::fmt;
+use std::collections::BTreeSet;
 use std::io::Error as IoError;
 use ethkey;
 use ethcrypto;
@@ -24,9 +25,11 @@ pub use super::types::all::{NodeId, DocumentEncryptedKeyShadow};
 pub use super::acl_storage::AclStorage;
 pub use super::key_storage::{KeyStorage, DocumentKeyShare};
 pub use super::serialization::{SerializableSignature, SerializableH256, SerializableSecret, SerializablePublic};
-pub use self::cluster::{ClusterCore, ClusterConfiguration, ClusterClient};
-pub use self::encryption_session::Session as EncryptionSession;
-pub use self::decryption_session::Session as DecryptionSession;
+pub use self::cluster::{Cluster, ClusterCore, ClusterConfiguration, ClusterClient, EventSink, NullCluster};
+pub use self::encryption_session::{Session as EncryptionSession, SessionImpl as EncryptionSessionImpl,
+	SessionParams as EncryptionSessionParams, SessionState as EncryptionSessionState};
+pub use self::decryption_session::{Session as DecryptionSession, SessionImpl as DecryptionSessionImpl,
+	SessionParams as DecryptionSessionParams, SessionState as DecryptionSessionState};
 
 #[cfg(test)]
 pub use super::key_storage::tests::DummyKeyStorage;
@@ -76,6 +79,24 @@ pub enum Error {
 	KeyStorage(String),
 	/// Acl storage error.
 	AccessDenied,
+	/// Maximal number of active sessions is reached.
+	TooManySessions,
+	/// Message, received from node, is larger than allowed.
+	MessageTooLarge,
+	/// Invalid cluster configuration.
+	InvalidConfiguration(String),
+	/// Node id, mentioned in the message, differs from the id of node, which has sent the message.
+	NodeIdMismatch,
+	/// Message has not been delivered to given nodes, because they're currently disconnected.
+	NodesDisconnected(BTreeSet<NodeId>),
+	/// Node is currently not accepting new sessions.
+	NotAcceptingSessions,
+	/// Required number of nodes has not been connected within given time.
+	QuorumTimeout,
+	/// Node, which is not a part of the session, has been passed.
+	InvalidNodeForRequest,
+	/// Response to Ping message has not been received within ping timeout.
+	PingTimeout,
 }
 
 impl From<ethkey::Error> for Error {
@@ -115,6 +136,15 @@ impl fmt::Display for Error {
 			Error::Serde(ref e) => write!(f, "serde error {}", e),
 			Error::KeyStorage(ref e) => write!(f, "key storage error {}", e),
 			Error::AccessDenied => write!(f, "Access denied"),
+			Error::TooManySessions => write!(f, "too many active sessions"),
+			Error::MessageTooLarge => write!(f, "message is too large"),
+			Error::InvalidConfiguration(ref e) => write!(f, "invalid configuration: {}", e),
+			Error::NodeIdMismatch => write!(f, "node id in message differs from sender node id"),
+			Error::NodesDisconnected(ref nodes) => write!(f, "nodes {:?} required for this operation are currently disconnected", nodes),
+			Error::NotAcceptingSessions => write!(f, "node is not accepting new sessions"),
+			Error::QuorumTimeout => write!(f, "required number of nodes has not been connected in time"),
+			Error::InvalidNodeForRequest => write!(f, "node is not a part of the session"),
+			Error::PingTimeout => write!(f, "ping has not been answered in time"),
 		}
 	}
 }
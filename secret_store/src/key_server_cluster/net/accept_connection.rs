@@ -24,20 +24,23 @@ use tokio_core::net::TcpStream;
 use ethkey::KeyPair;
 use key_server_cluster::{Error, NodeId};
 use key_server_cluster::io::{accept_handshake, Handshake, Deadline, deadline};
-use key_server_cluster::net::Connection;
+use key_server_cluster::net::{Connection, set_socket_options};
 
 /// Create future for accepting incoming connection.
-pub fn accept_connection(address: SocketAddr, stream: TcpStream, handle: &Handle, self_key_pair: KeyPair, trusted_nodes: BTreeSet<NodeId>) -> Deadline<AcceptConnection> {
+pub fn accept_connection(address: SocketAddr, stream: TcpStream, handle: &Handle, self_key_pair: KeyPair, trusted_nodes: BTreeSet<NodeId>, timeout: Duration, tcp_keepalive_interval: Duration) -> Deadline<AcceptConnection> {
+	let error = set_socket_options(&stream, tcp_keepalive_interval).err();
 	let accept = AcceptConnection {
+		error: error,
 		handshake: accept_handshake(stream, self_key_pair, trusted_nodes),
 		address: address,
 	};
 
-	deadline(Duration::new(5, 0), handle, accept).expect("Failed to create timeout")
+	deadline(timeout, handle, accept).expect("Failed to create timeout")
 }
 
 /// Future for accepting incoming connection.
 pub struct AcceptConnection {
+	error: Option<io::Error>,
 	handshake: Handshake<TcpStream>,
 	address: SocketAddr,
 }
@@ -47,6 +50,10 @@ impl Future for AcceptConnection {
 	type Error = io::Error;
 
 	fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+		if let Some(err) = self.error.take() {
+			return Err(err);
+		}
+
 		let (stream, result) = try_ready!(self.handshake.poll());
 		let result = match result {
 			Ok(result) => result,
@@ -24,18 +24,19 @@ use tokio_core::net::{TcpStream, TcpStreamNew};
 use ethkey::KeyPair;
 use key_server_cluster::{Error, NodeId};
 use key_server_cluster::io::{handshake, Handshake, Deadline, deadline};
-use key_server_cluster::net::Connection;
+use key_server_cluster::net::{Connection, set_socket_options};
 
 /// Create future for connecting to other node.
-pub fn connect(address: &SocketAddr, handle: &Handle, self_key_pair: KeyPair, trusted_nodes: BTreeSet<NodeId>) -> Deadline<Connect> {
+pub fn connect(address: &SocketAddr, handle: &Handle, self_key_pair: KeyPair, trusted_nodes: BTreeSet<NodeId>, timeout: Duration, tcp_keepalive_interval: Duration) -> Deadline<Connect> {
 	let connect = Connect {
 		state: ConnectState::TcpConnect(TcpStream::connect(address, handle)),
 		address: address.clone(),
 		self_key_pair: self_key_pair,
 		trusted_nodes: trusted_nodes,
+		tcp_keepalive_interval: tcp_keepalive_interval,
 	};
 
-	deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
+	deadline(timeout, handle, connect).expect("Failed to create timeout")
 }
 
 enum ConnectState {
@@ -50,6 +51,7 @@ pub struct Connect {
 	address: SocketAddr,
 	self_key_pair: KeyPair,
 	trusted_nodes: BTreeSet<NodeId>,
+	tcp_keepalive_interval: Duration,
 }
 
 impl Future for Connect {
@@ -60,6 +62,7 @@ impl Future for Connect {
 		let (next, result) = match self.state {
 			ConnectState::TcpConnect(ref mut future) => {
 				let stream = try_ready!(future.poll());
+				set_socket_options(&stream, self.tcp_keepalive_interval)?;
 				let handshake = handshake(stream, self.self_key_pair.clone(), self.trusted_nodes.clone());
 				(ConnectState::Handshake(handshake), Async::NotReady)
 			},
@@ -14,6 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::io;
+use std::time::Duration;
+use tokio_core::net::TcpStream;
+
 mod accept_connection;
 mod connect;
 mod connection;
@@ -21,3 +25,10 @@ mod connection;
 pub use self::accept_connection::{AcceptConnection, accept_connection};
 pub use self::connect::{Connect, connect};
 pub use self::connection::Connection;
+
+/// Disable Nagle's algorithm (protocol messages are small) && enable TCP keep-alive on the stream.
+fn set_socket_options(stream: &TcpStream, tcp_keepalive_interval: Duration) -> io::Result<()> {
+	let tcp_keepalive_interval_ms = tcp_keepalive_interval.as_secs() * 1000 + (tcp_keepalive_interval.subsec_nanos() / 1_000_000) as u64;
+	stream.set_nodelay(true)?;
+	stream.set_keepalive_ms(Some(tcp_keepalive_interval_ms as u32))
+}
@@ -15,13 +15,17 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 extern crate byteorder;
+#[cfg(feature = "compression")]
+extern crate flate2;
 #[macro_use]
 extern crate log;
 #[macro_use]
 extern crate futures;
 extern crate futures_cpupool;
 extern crate hyper;
+extern crate net2;
 extern crate parking_lot;
+extern crate rand;
 extern crate rustc_serialize;
 extern crate serde;
 extern crate serde_json;
@@ -63,6 +67,11 @@ use ethcore::client::Client;
 pub use types::all::{DocumentAddress, DocumentKey, DocumentEncryptedKey, RequestSignature, Public,
 	Error, NodeAddress, ServiceConfiguration, ClusterConfiguration};
 pub use traits::{KeyServer};
+pub use acl_storage::AclStorage;
+pub use key_storage::{KeyStorage, DocumentKeyShare};
+pub use key_server_cluster::{Cluster, NullCluster, Error as ClusterError,
+	EncryptionSession, EncryptionSessionImpl, EncryptionSessionParams, EncryptionSessionState,
+	DecryptionSession, DecryptionSessionImpl, DecryptionSessionParams, DecryptionSessionState};
 
 /// Start new key server instance
 pub fn start(client: Arc<Client>, config: ServiceConfiguration) -> Result<Box<KeyServer>, Error> {
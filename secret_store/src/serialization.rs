@@ -71,7 +71,7 @@ impl Deserialize for SerializableBytes {
 		where D: Deserializer
 	{
 		let s = String::deserialize(deserializer)?;
-		if s.len() >= 2 && &s[0..2] == "0x" && s.len() & 1 == 0 {
+		if s.starts_with("0x") && s.len() & 1 == 0 {
 			let data = s[2..].from_hex().map_err(SerdeError::custom)?;
 			Ok(SerializableBytes(data))
 		} else {
@@ -124,7 +124,7 @@ impl Deserialize for SerializableSignature {
 			}
 
 			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> where E: SerdeError {
-				if value.len() >= 2 && &value[0..2] == "0x" && value.len() & 1 == 0 {
+				if value.starts_with("0x") && value.len() & 1 == 0 {
 					value[2..].parse().map(|s| SerializableSignature(s)).map_err(SerdeError::custom)
 				} else {
 					Err(SerdeError::custom("invalid format"))
@@ -184,7 +184,7 @@ impl Deserialize for SerializableH256 {
 			}
 
 			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> where E: SerdeError {
-				if value.len() >= 2 && &value[0..2] == "0x" && value.len() & 1 == 0 {
+				if value.starts_with("0x") && value.len() & 1 == 0 {
 					value[2..].parse().map(|s| SerializableH256(s)).map_err(SerdeError::custom)
 				} else {
 					Err(SerdeError::custom("invalid format"))
@@ -244,7 +244,7 @@ impl Deserialize for SerializableSecret {
 			}
 
 			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> where E: SerdeError {
-				if value.len() >= 2 && &value[0..2] == "0x" && value.len() & 1 == 0 {
+				if value.starts_with("0x") && value.len() & 1 == 0 {
 					value[2..].parse().map(|s| SerializableSecret(s)).map_err(SerdeError::custom)
 				} else {
 					Err(SerdeError::custom("invalid format"))
@@ -324,7 +324,7 @@ impl Deserialize for SerializablePublic {
 			}
 
 			fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> where E: SerdeError {
-				if value.len() >= 2 && &value[0..2] == "0x" && value.len() & 1 == 0 {
+				if value.starts_with("0x") && value.len() & 1 == 0 {
 					value[2..].parse().map(|s| SerializablePublic(s)).map_err(SerdeError::custom)
 				} else {
 					Err(SerdeError::custom("invalid format"))